@@ -1,8 +1,9 @@
 use bevy::prelude::*;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use directories::ProjectDirs;
 use std::{
     path::PathBuf,
+    str::FromStr,
     sync::{LazyLock, Mutex},
 };
 
@@ -13,12 +14,109 @@ use std::{
 pub struct CLIArgs {
     #[arg(short, long, default_value = DEFAULT_CONFIG_PATH.lock().unwrap().display().to_string())]
     config_file: PathBuf,
+
+    /// Run the game loop without a window or renderer
+    #[arg(long, default_value_t = false)]
+    headless: bool,
+
+    /// The number of ticks to run before exiting in headless mode
+    #[arg(long, requires = "headless")]
+    ticks: Option<u32>,
+
+    /// Record every frame's inputs into the file
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Play recorded inputs back from the file
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Override a config value on top of the configuration file, e.g. `player.mouse.x_reverse=true`
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<Override>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ConfigCommand {
+    /// Print the effective configs as TOML, with the overrides applied
+    Dump,
+
+    /// Check a configuration file, and exit with an error if it is invalid
+    Validate { file: PathBuf },
+
+    /// Print the path of the configuration file
+    Path,
+}
+
+/// A `--set` argument, which replaces the value at the key path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Override {
+    pub key: String,
+    pub value: toml::Value,
+}
+
+impl FromStr for Override {
+    type Err = String;
+
+    /// The value is parsed as a TOML value, such as `true`, `0.5` or `[{ Key = "KeyJ" }]`.
+    /// Anything else, such as `KeyJ`, is a string.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("`{s}` is not KEY=VALUE"))?;
+        let key: String = key.trim().to_string();
+
+        if key.is_empty() || key.split('.').any(str::is_empty) {
+            return Err(format!("`{key}` is not a key path"));
+        }
+
+        let value: toml::Value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+        Ok(Self { key, value })
+    }
 }
 
 impl CLIArgs {
     pub fn config_file(&self) -> PathBuf {
         self.config_file.clone()
     }
+
+    pub fn headless(&self) -> bool {
+        self.headless
+    }
+
+    pub fn ticks(&self) -> Option<u32> {
+        self.ticks
+    }
+
+    pub fn record(&self) -> Option<PathBuf> {
+        self.record.clone()
+    }
+
+    pub fn replay(&self) -> Option<PathBuf> {
+        self.replay.clone()
+    }
+
+    pub fn overrides(&self) -> &[Override] {
+        &self.overrides
+    }
+
+    pub fn command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
 }
 
 /// Default Configuration Path, using directories crate to calculate ProjectDirs (~/.config/spacerobo)
@@ -35,16 +133,94 @@ static DEFAULT_CONFIG_PATH: LazyLock<Mutex<PathBuf>> = LazyLock::new(|| {
 #[cfg(test)]
 mod tests {
     mod cli_args {
-        use crate::cli::CLIArgs;
+        use crate::cli::{CLIArgs, Command, ConfigCommand};
+        use clap::Parser;
         use std::path::PathBuf;
 
         #[test]
         fn config_file() {
             let cli_args: CLIArgs = CLIArgs {
                 config_file: PathBuf::new(),
+                headless: false,
+                ticks: None,
+                record: None,
+                replay: None,
+                overrides: Vec::new(),
+                command: None,
             };
 
             assert_eq!(cli_args.config_file(), PathBuf::new());
         }
+
+        #[test]
+        fn headless() {
+            let cli_args: CLIArgs = CLIArgs::parse_from(["spr", "--headless", "--ticks", "6000"]);
+
+            assert!(cli_args.headless());
+            assert_eq!(cli_args.ticks(), Some(6000));
+        }
+
+        #[test]
+        fn ticks_requires_headless() {
+            let result = CLIArgs::try_parse_from(["spr", "--ticks", "6000"]);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn replay() {
+            let cli_args: CLIArgs = CLIArgs::parse_from(["spr", "--replay", "bug.ron"]);
+
+            assert_eq!(cli_args.replay(), Some(PathBuf::from("bug.ron")));
+            assert_eq!(cli_args.record(), None);
+        }
+
+        #[test]
+        fn record_conflicts_with_replay() {
+            let result = CLIArgs::try_parse_from(["spr", "--record", "a.ron", "--replay", "b.ron"]);
+
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn config_subcommands() {
+            let dump: CLIArgs = CLIArgs::parse_from(["spr", "config", "dump"]);
+            let validate: CLIArgs = CLIArgs::parse_from(["spr", "config", "validate", "a.toml"]);
+
+            assert_eq!(dump.command(), Some(&Command::Config(ConfigCommand::Dump)));
+            assert_eq!(
+                validate.command(),
+                Some(&Command::Config(ConfigCommand::Validate {
+                    file: PathBuf::from("a.toml")
+                }))
+            );
+        }
+
+        /// `--set` is repeatable, and applies to subcommands too
+        #[test]
+        fn overrides() {
+            let cli_args: CLIArgs = CLIArgs::parse_from([
+                "spr",
+                "--set",
+                "player.mouse.x_reverse=true",
+                "--set",
+                "player.keyboard.forward=KeyI",
+                "config",
+                "dump",
+            ]);
+            let overrides = cli_args.overrides();
+
+            assert_eq!(overrides.len(), 2);
+            assert_eq!(overrides[0].key, "player.mouse.x_reverse");
+            assert_eq!(overrides[0].value, toml::Value::Boolean(true));
+            assert_eq!(overrides[1].value, toml::Value::String("KeyI".to_string()));
+        }
+
+        #[test]
+        fn override_without_value() {
+            let result = CLIArgs::try_parse_from(["spr", "--set", "player.mouse.x_reverse"]);
+
+            assert!(result.is_err());
+        }
     }
 }
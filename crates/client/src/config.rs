@@ -0,0 +1,413 @@
+//! # Configuration file loading
+//!
+//! Loads GameConfigs from the configuration file, applies `--set` overrides and validates it.
+//! A broken file stops the client with the reason, instead of silently falling back to the defaults.
+
+use crate::cli::Override;
+use spacerobo_commons::configs::{
+    GameConfigs,
+    migration::CURRENT_VERSION,
+    validation::{Issue, Severity},
+};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use toml::{Table, Value};
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{}: {reason}", path.display())]
+    Load { path: PathBuf, reason: String },
+
+    #[error("{}: invalid configs\n{}", path.display(), list(issues))]
+    Invalid { path: PathBuf, issues: Vec<Issue> },
+
+    #[error("--set {key}: {reason}")]
+    Override { key: String, reason: String },
+}
+
+/// One issue per line
+fn list(issues: &[Issue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("  {issue}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Loads and validates the configuration file, which is created with the defaults if it doesn't exist.
+/// Files of older versions are migrated, and then the overrides are applied.
+/// Returns the configs with the warnings, or an error if the file can't be parsed or has an invalid value.
+pub fn load(path: &Path, overrides: &[Override]) -> Result<(GameConfigs, Vec<Issue>), ConfigError> {
+    let load_error = |reason: String| ConfigError::Load {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    if !path.exists() {
+        confy::store_path(path, GameConfigs::default()).map_err(|e| load_error(chain(&e)))?;
+    }
+
+    let text: String = std::fs::read_to_string(path).map_err(|e| load_error(e.to_string()))?;
+
+    check(path, &text, overrides)
+}
+
+/// Validates the configuration file with the overrides, without creating it.
+/// Returns the warnings, or an error if the file can't be parsed or has an invalid value.
+pub fn validate(path: &Path, overrides: &[Override]) -> Result<Vec<Issue>, ConfigError> {
+    let text: String = std::fs::read_to_string(path).map_err(|e| ConfigError::Load {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+
+    check(path, &text, overrides).map(|(_, warnings)| warnings)
+}
+
+/// The configs which the game would run with, without creating the configuration file.
+/// A missing file stands for the defaults.
+pub fn effective(
+    path: &Path,
+    overrides: &[Override],
+) -> Result<(GameConfigs, Vec<Issue>), ConfigError> {
+    if !path.exists() {
+        return check_configs(path, GameConfigs::default(), CURRENT_VERSION, overrides);
+    }
+
+    let text: String = std::fs::read_to_string(path).map_err(|e| ConfigError::Load {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+
+    check(path, &text, overrides)
+}
+
+fn check(
+    path: &Path,
+    text: &str,
+    overrides: &[Override],
+) -> Result<(GameConfigs, Vec<Issue>), ConfigError> {
+    let (configs, version): (GameConfigs, u32) =
+        GameConfigs::from_toml(text).map_err(|e| ConfigError::Load {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+
+    check_configs(path, configs, version, overrides)
+}
+
+/// Applies the overrides to the configs parsed from the file of the version, and validates them
+fn check_configs(
+    path: &Path,
+    configs: GameConfigs,
+    version: u32,
+    overrides: &[Override],
+) -> Result<(GameConfigs, Vec<Issue>), ConfigError> {
+    let configs: GameConfigs = apply(configs, overrides)?;
+
+    let (errors, mut warnings): (Vec<Issue>, Vec<Issue>) =
+        configs.validate().into_iter().partition(Issue::is_error);
+
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid {
+            path: path.to_path_buf(),
+            issues: errors,
+        });
+    }
+
+    if version < CURRENT_VERSION {
+        warnings.insert(
+            0,
+            Issue {
+                severity: Severity::Warning,
+                path: "version".to_string(),
+                reason: format!(
+                    "the file is written in version {version}, and migrated to {CURRENT_VERSION}"
+                ),
+            },
+        );
+    }
+
+    Ok((configs, warnings))
+}
+
+/// Replaces the values at the overrides' key paths, in order.
+/// A key which GameConfigs doesn't have is an error, rather than being ignored.
+pub fn apply(configs: GameConfigs, overrides: &[Override]) -> Result<GameConfigs, ConfigError> {
+    let mut configs: GameConfigs = configs;
+
+    for Override { key, value } in overrides {
+        let error = |reason: String| ConfigError::Override {
+            key: key.clone(),
+            reason,
+        };
+        let path: Vec<&str> = key.split('.').collect();
+
+        let mut table: Value = Value::try_from(&configs).map_err(|e| error(e.to_string()))?;
+        set(&mut table, &path, value.clone()).map_err(error)?;
+        configs = table
+            .try_into()
+            .map_err(|e: toml::de::Error| error(e.to_string()))?;
+
+        // Unknown keys are dropped by deserialization, so the key must survive a round trip
+        let written: Value = Value::try_from(&configs).map_err(|e| error(e.to_string()))?;
+        if get(&written, &path).is_none() {
+            return Err(error("is not a config key".to_string()));
+        }
+    }
+
+    Ok(configs)
+}
+
+/// Sets the value at the key path, creating missing tables on the way
+fn set(table: &mut Value, path: &[&str], value: Value) -> Result<(), String> {
+    let Some((last, parents)) = path.split_last() else {
+        return Err("is empty".to_string());
+    };
+
+    let mut current: &mut Value = table;
+    for (i, key) in parents.iter().enumerate() {
+        current = current
+            .as_table_mut()
+            .ok_or_else(|| format!("{} is not a table", path[..i].join(".")))?
+            .entry(*key)
+            .or_insert_with(|| Value::Table(Table::new()));
+    }
+
+    current
+        .as_table_mut()
+        .ok_or_else(|| format!("{} is not a table", parents.join(".")))?
+        .insert(last.to_string(), value);
+
+    Ok(())
+}
+
+fn get<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| value.get(*key))
+}
+
+/// The error with its sources, which keep the details such as an IO error
+fn chain(e: &dyn std::error::Error) -> String {
+    let mut reason: String = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        reason = format!("{reason}: {e}");
+        source = e.source();
+    }
+
+    reason
+}
+
+#[cfg(test)]
+mod tests {
+    mod load {
+        use crate::config::{ConfigError, load};
+        use bevy::prelude::*;
+        use spacerobo_commons::configs::GameConfigs;
+        use std::path::PathBuf;
+
+        /// A config file unique to the test
+        fn file(name: &str, content: &str) -> PathBuf {
+            let path: PathBuf =
+                std::env::temp_dir().join(format!("spacerobo-{}-{name}.toml", std::process::id()));
+            std::fs::write(&path, content).unwrap();
+
+            path
+        }
+
+        /// A typo is reported with the file & the line, instead of being replaced by the defaults
+        #[test]
+        fn parse_error() {
+            let path: PathBuf = file("parse_error", "[player.keyboard]\nforward = \"KeyWW\"\n");
+
+            let error: ConfigError = load(&path, &[]).unwrap_err();
+            let message: String = error.to_string();
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(matches!(error, ConfigError::Load { .. }));
+            assert!(
+                message.starts_with(&path.display().to_string()),
+                "{message}"
+            );
+            assert!(message.contains("line 2"), "{message}");
+        }
+
+        /// A misspelled key is an error, rather than being ignored
+        #[test]
+        fn unknown_key() {
+            let path: PathBuf = file(
+                "unknown_key",
+                "version = 1\n\n[player.keyboard]\nfowrard = \"KeyQ\"\n",
+            );
+
+            let error: ConfigError = load(&path, &[]).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(matches!(error, ConfigError::Load { .. }));
+            assert!(
+                error
+                    .to_string()
+                    .ends_with("unknown key player.keyboard.fowrard"),
+                "{error}"
+            );
+        }
+
+        /// Invalid values are reported with their key paths
+        #[test]
+        fn invalid() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.keyboard.dash = KeyCode::KeyW;
+            configs.player.robo.fuel.capacity = 0.0;
+            let path: PathBuf = file("invalid", "");
+            confy::store_path(&path, &configs).unwrap();
+
+            let error: ConfigError = load(&path, &[]).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+
+            let ConfigError::Invalid { issues, .. } = &error else {
+                panic!("{error}");
+            };
+            let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec!["player.keyboard.dash", "player.robo.fuel.capacity"]
+            );
+        }
+
+        /// Old files are migrated with a warning
+        #[test]
+        fn migrated() {
+            let path: PathBuf = file(
+                "migrated",
+                "[player.keyboard]\ntoggle_firemode = \"KeyG\"\n",
+            );
+
+            let (loaded, warnings) = load(&path, &[]).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded.player.keyboard.toggle_firemode, KeyCode::KeyG);
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].path, "version");
+        }
+
+        /// A missing file is created with the defaults
+        #[test]
+        fn missing() {
+            let path: PathBuf = file("missing", "");
+            std::fs::remove_file(&path).unwrap();
+
+            let (loaded, warnings) = load(&path, &[]).unwrap();
+            let (reloaded, _) = load(&path, &[]).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded, GameConfigs::default());
+            assert_eq!(reloaded, loaded);
+            assert!(warnings.is_empty());
+        }
+
+        /// Warnings don't stop loading
+        #[test]
+        fn warnings() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.robo.resistances.kinetic = 2.0;
+            let path: PathBuf = file("warnings", "");
+            confy::store_path(&path, &configs).unwrap();
+
+            let (loaded, warnings) = load(&path, &[]).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded, configs);
+            assert_eq!(warnings.len(), 1);
+        }
+    }
+
+    mod effective {
+        use crate::{cli::Override, config::effective};
+        use spacerobo_commons::configs::GameConfigs;
+        use std::path::PathBuf;
+
+        /// A missing file is the defaults with the overrides, and isn't created
+        #[test]
+        fn missing() {
+            let path: PathBuf = std::env::temp_dir().join(format!(
+                "spacerobo-{}-effective-missing.toml",
+                std::process::id()
+            ));
+            let overrides: Vec<Override> = vec!["player.mouse.x_reverse=true".parse().unwrap()];
+
+            let (configs, warnings) = effective(&path, &overrides).unwrap();
+
+            assert!(!path.exists());
+            assert!(configs.player.mouse.x_reverse);
+            assert_eq!(
+                configs.player.keyboard,
+                GameConfigs::default().player.keyboard
+            );
+            assert!(warnings.is_empty());
+        }
+    }
+
+    mod apply {
+        use crate::{
+            cli::Override,
+            config::{ConfigError, apply},
+        };
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            configs::GameConfigs,
+            input::{Action, Binding},
+        };
+
+        fn overrides(args: &[&str]) -> Vec<Override> {
+            args.iter().map(|arg| arg.parse().unwrap()).collect()
+        }
+
+        /// Values are replaced at their key paths, and bare words are strings
+        #[test]
+        fn values() {
+            let configs: GameConfigs = apply(
+                GameConfigs::default(),
+                &overrides(&[
+                    "player.mouse.x_reverse=true",
+                    "player.keyboard.forward=KeyI",
+                    "player.robo.thruster.force.accelerate=1",
+                    "player.actions.fire=[{ Key = \"KeyJ\" }]",
+                ]),
+            )
+            .unwrap();
+
+            assert!(configs.player.mouse.x_reverse);
+            assert_eq!(configs.player.keyboard.forward, KeyCode::KeyI);
+            assert_eq!(configs.player.robo.thruster.force.accelerate, 1.0);
+            assert_eq!(
+                configs.player.bindings(Action::Fire),
+                vec![Binding::Key(KeyCode::KeyJ)]
+            );
+        }
+
+        /// A typo in the key is an error, rather than being ignored
+        #[test]
+        fn unknown_key() {
+            let error: ConfigError = apply(
+                GameConfigs::default(),
+                &overrides(&["player.mouse.x_revers=true"]),
+            )
+            .unwrap_err();
+
+            assert_eq!(
+                error.to_string(),
+                "--set player.mouse.x_revers: is not a config key"
+            );
+        }
+
+        /// A value of the wrong type is an error
+        #[test]
+        fn wrong_type() {
+            let result = apply(
+                GameConfigs::default(),
+                &overrides(&["player.mouse.x_reverse=KeyI"]),
+            );
+
+            assert!(matches!(result, Err(ConfigError::Override { .. })));
+        }
+    }
+}
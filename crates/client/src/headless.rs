@@ -0,0 +1,443 @@
+//! # Headless simulation mode
+//!
+//! Runs Spacerobo's game loop without a window or renderer.
+//! This is useful for scripted sessions and integration tests on machines which have no GPU.
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    asset::AssetPlugin,
+    audio::{AudioLoader, PlaybackMode},
+    camera::visibility::VisibilityPlugin,
+    input::InputPlugin,
+    prelude::*,
+    scene::ScenePlugin,
+    state::app::StatesPlugin,
+    time::TimeUpdateStrategy,
+};
+use spacerobo_commons::GameMode;
+use std::time::Duration;
+
+/// The time step which a headless frame advances the game clock
+pub const HEADLESS_TIME_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// A plugin to run Spacerobo without a window or renderer.
+/// It replaces DefaultPlugins with MinimalPlugins and the asset, scene, state, input, transform & visibility plugins.
+#[derive(Debug, Clone)]
+pub struct HeadlessPlugin {
+    /// The number of ticks to run before exiting. `None` means running forever.
+    pub ticks: Option<u32>,
+
+    /// The GameMode to start with. Replays need GameMode::Title because recordings start there.
+    pub initial_state: GameMode,
+}
+
+impl HeadlessPlugin {
+    pub fn new(ticks: Option<u32>) -> Self {
+        Self {
+            ticks,
+            initial_state: GameMode::InGame,
+        }
+    }
+}
+
+impl std::default::Default for HeadlessPlugin {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            AssetPlugin::default(),
+            ScenePlugin,
+            StatesPlugin,
+            InputPlugin,
+            TransformPlugin,
+            VisibilityPlugin,
+        ));
+
+        // Assets which are usually initialized by the render & audio plugins
+        app.init_asset::<Mesh>();
+        app.init_asset::<StandardMaterial>();
+        app.init_asset::<AudioSource>();
+        app.init_asset_loader::<AudioLoader>();
+
+        // Without the audio plugin, nothing plays sounds nor despawns the ones which despawn when they finish
+        app.add_systems(Last, sound_reaping_system);
+
+        // Advance the game clock by a fixed step each frame, regardless of the wall clock
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(HEADLESS_TIME_STEP));
+
+        // There is no one to press a key on the title screen, unless a replay does
+        app.insert_state(self.initial_state.clone());
+
+        if let Some(ticks) = self.ticks {
+            app.insert_resource(TickLimit { rest: ticks });
+            app.add_systems(Last, tick_limit_system);
+        }
+    }
+}
+
+/// Finishes sound effects at once, as they can't be played without an audio device.
+/// Only sounds which the audio plugin would clean up are reaped, so a sound entity left behind still shows up.
+fn sound_reaping_system(
+    mut commands: Commands,
+    sound_query: Query<(Entity, &PlaybackSettings), With<AudioPlayer>>,
+) {
+    for (entity, settings) in sound_query.iter() {
+        match settings.mode {
+            PlaybackMode::Despawn => commands.entity(entity).despawn(),
+            PlaybackMode::Remove => {
+                commands
+                    .entity(entity)
+                    .remove::<(AudioPlayer, PlaybackSettings)>();
+            }
+            PlaybackMode::Once | PlaybackMode::Loop => (),
+        }
+    }
+}
+
+/// The rest of ticks to run before exiting
+#[derive(Debug, Resource)]
+pub struct TickLimit {
+    pub rest: u32,
+}
+
+/// Exits the app when the rest of ticks is depleted
+fn tick_limit_system(mut tick_limit: ResMut<TickLimit>, mut exit: MessageWriter<AppExit>) {
+    tick_limit.rest = tick_limit.rest.saturating_sub(1);
+
+    if tick_limit.rest == 0 {
+        info!("Reached the tick limit, exiting...");
+        exit.write(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod headless_plugin {
+        use crate::headless::HeadlessPlugin;
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            Controllable, ControllablePlugin, GameMode, configs::GameConfigs, input::ActionPlugin,
+            testing::update,
+        };
+        use spacerobo_shooting_range_plugin::ShootingRangePlugin;
+
+        fn app() -> App {
+            let mut app: App = App::new();
+            app.add_plugins((
+                HeadlessPlugin::default(),
+                PhysicsPlugins::default(),
+                ShootingRangePlugin,
+                ControllablePlugin,
+                ActionPlugin,
+            ))
+            .insert_resource(GameConfigs::default());
+
+            app
+        }
+
+        /// The game starts in the shooting range and spawns the player
+        #[test]
+        fn starts_in_game() {
+            let mut app: App = app();
+            update(&mut app, 10);
+
+            assert_eq!(
+                *app.world().resource::<State<GameMode>>().get(),
+                GameMode::InGame
+            );
+
+            let mut query = app.world_mut().query::<&Controllable>();
+            assert_eq!(query.iter(app.world()).count(), 1);
+        }
+
+        /// The same recording produces the same outcome
+        #[test]
+        fn replay_is_deterministic() {
+            use spacerobo_commons::{
+                Hp,
+                replay::{InputFrame, InputPlaybackPlugin, InputRecording},
+            };
+            use std::time::Duration;
+
+            // Fly forward while shooting and turning, then stop shooting
+            let frames: Vec<InputFrame> = (0..120)
+                .map(|i| InputFrame {
+                    delta: Duration::from_millis(if i % 3 == 0 { 7 } else { 16 }),
+                    keys: vec![KeyCode::KeyW],
+                    mouse_buttons: if i < 90 {
+                        vec![MouseButton::Left]
+                    } else {
+                        Vec::new()
+                    },
+                    mouse_motion: Vec2::new(2.0, 1.0),
+                    gamepads: Vec::new(),
+                })
+                .collect();
+            let recording: InputRecording = InputRecording { frames };
+
+            let run = || {
+                let mut app: App = app();
+                app.add_plugins(InputPlaybackPlugin {
+                    recording: recording.clone(),
+                });
+
+                update(&mut app, recording.frames.len());
+
+                let transform: Transform = *app
+                    .world_mut()
+                    .query_filtered::<&Transform, With<Controllable>>()
+                    .single(app.world())
+                    .unwrap();
+                let hp_entities: usize = app.world_mut().query::<&Hp>().iter(app.world()).count();
+
+                (transform, hp_entities)
+            };
+
+            assert_eq!(run(), run());
+        }
+
+        /// Counts of a sustained fire
+        #[derive(Resource, Default, Debug)]
+        struct Fire {
+            /// Bullets, beams & missiles shot
+            shots: usize,
+
+            /// Meshes & materials added to the assets
+            meshes: usize,
+            materials: usize,
+        }
+
+        fn count_added_assets_system(
+            mut fire: ResMut<Fire>,
+            mut meshes: MessageReader<AssetEvent<Mesh>>,
+            mut materials: MessageReader<AssetEvent<StandardMaterial>>,
+        ) {
+            fire.meshes += meshes
+                .read()
+                .filter(|event| matches!(event, AssetEvent::Added { .. }))
+                .count();
+            fire.materials += materials
+                .read()
+                .filter(|event| matches!(event, AssetEvent::Added { .. }))
+                .count();
+        }
+
+        fn entities(app: &mut App) -> usize {
+            app.world_mut().query::<Entity>().iter(app.world()).count()
+        }
+
+        /// Pulls the trigger of the weapon every other tick for 20 seconds, reloading every 2 seconds.
+        /// Returns the counts of the latter 10 seconds, with the number of entities at its start & end.
+        fn sustained_fire(weapon: &str) -> (Fire, usize, usize) {
+            use spacerobo_commons::replay::{InputFrame, InputPlaybackPlugin, InputRecording};
+            use spacerobo_gun::{bullet::Common as CommonBullet, hitscan::Beam, missile::Missile};
+            use std::time::Duration;
+
+            let frames: Vec<InputFrame> = (0..1200)
+                .map(|i| InputFrame {
+                    delta: Duration::from_millis(16),
+                    keys: if i % 120 == 119 {
+                        vec![KeyCode::KeyR]
+                    } else {
+                        Vec::new()
+                    },
+                    mouse_buttons: if i % 2 == 0 {
+                        vec![MouseButton::Left]
+                    } else {
+                        Vec::new()
+                    },
+                    mouse_motion: Vec2::ZERO,
+                    gamepads: Vec::new(),
+                })
+                .collect();
+
+            let mut app: App = app();
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.robo.weapon = weapon.to_string();
+            app.insert_resource(configs);
+            app.add_plugins(InputPlaybackPlugin {
+                recording: InputRecording { frames },
+            });
+            app.init_resource::<Fire>();
+            app.add_systems(Last, count_added_assets_system);
+            app.add_observer(
+                |_: On<Add, (CommonBullet, Beam, Missile)>, mut fire: ResMut<Fire>| {
+                    fire.shots += 1;
+                },
+            );
+
+            // Bullets live 2 seconds, so the pool is warmed up after 10 seconds
+            update(&mut app, 600);
+            let warm_up: Fire = std::mem::take(&mut *app.world_mut().resource_mut::<Fire>());
+            assert!(warm_up.shots > 0, "{weapon}: {warm_up:?}");
+            let start: usize = entities(&mut app);
+
+            for _ in 0..600 {
+                app.update();
+            }
+            let end: usize = entities(&mut app);
+            let fire: Fire = std::mem::take(&mut *app.world_mut().resource_mut::<Fire>());
+            assert!(fire.shots > 0, "{weapon}: {fire:?}");
+
+            (fire, start, end)
+        }
+
+        /// Sustained full auto fire doesn't grow entities & assets, thanks to the bullet pool
+        #[test]
+        fn sustained_fire_steady_state() {
+            let (fire, entities, later_entities) = sustained_fire("weapons/rifle.weapon.ron");
+
+            // Every entity counts, including sound effects & anything else spawned per shot
+            assert!(later_entities <= entities, "{later_entities} > {entities}");
+            assert_eq!((fire.meshes, fire.materials), (0, 0), "{fire:?}");
+        }
+
+        /// Sustained railgun fire shares a mesh & a material among the beams
+        #[test]
+        fn sustained_railgun_fire() {
+            let (fire, _, _) = sustained_fire("weapons/railgun.weapon.ron");
+
+            assert_eq!((fire.meshes, fire.materials), (0, 0), "{fire:?}");
+        }
+
+        /// Sustained missile fire shares a mesh & a material among the missiles
+        #[test]
+        fn sustained_missile_fire() {
+            let (fire, _, _) = sustained_fire("weapons/missile.weapon.ron");
+
+            assert_eq!((fire.meshes, fire.materials), (0, 0), "{fire:?}");
+        }
+
+        /// A gamepad's sticks thrust & turn the robo in proportion to how far they are pushed
+        #[test]
+        fn gamepad_flight() {
+            use bevy::input::gamepad::Gamepad;
+
+            let run = |stick: f32| {
+                let mut app: App = app();
+
+                // Forward on the left stick & right on the right stick
+                let mut gamepad: Gamepad = Gamepad::default();
+                gamepad.analog_mut().set(GamepadAxis::LeftStickY, stick);
+                gamepad.analog_mut().set(GamepadAxis::RightStickX, stick);
+                app.world_mut().spawn(gamepad);
+
+                update(&mut app, 30);
+
+                let (linear, angular) = app
+                    .world_mut()
+                    .query_filtered::<(&LinearVelocity, &AngularVelocity), With<Controllable>>()
+                    .single(app.world())
+                    .unwrap();
+
+                (linear.0, angular.0)
+            };
+
+            // Half of the range outside the default deadzone
+            let (half_linear, half_angular) = run(0.575);
+            let (full_linear, full_angular) = run(1.0);
+
+            assert!(full_linear.length() > 1.0, "{full_linear}");
+            assert!(full_angular.y < 0.0, "{full_angular}");
+            assert!(
+                (full_linear.length() - half_linear.length() * 2.0).abs() < 0.5,
+                "{full_linear} {half_linear}"
+            );
+            assert!(
+                (full_angular.y - half_angular.y * 2.0).abs() < 1e-2,
+                "{full_angular} {half_angular}"
+            );
+        }
+
+        /// Ramming a target in the shooting range deals Collision damage to both, and more to the target
+        #[test]
+        fn ramming() {
+            use spacerobo_commons::{Damage, DamageKind};
+
+            /// Collision damage dealt, by target
+            #[derive(Resource, Default)]
+            struct Rams(Vec<(Entity, f32)>);
+
+            let mut app: App = app();
+            app.init_resource::<Rams>();
+            app.add_observer(|damage: On<Damage>, mut rams: ResMut<Rams>| {
+                if damage.kind == DamageKind::Collision {
+                    rams.0.push((damage.target, damage.amount));
+                }
+            });
+            update(&mut app, 10);
+
+            // Back into the red target at (10, 10, 10), so the gun in front of the robo doesn't touch it
+            let robo: Entity = app
+                .world_mut()
+                .query_filtered::<Entity, With<Controllable>>()
+                .single(app.world())
+                .unwrap();
+            app.world_mut().entity_mut(robo).insert((
+                Transform::from_xyz(10.0, 10.0, 4.0),
+                LinearVelocity(Vec3::Z * 20.0),
+            ));
+
+            for _ in 0..60 {
+                app.update();
+            }
+
+            let rams: &Vec<(Entity, f32)> = &app.world().resource::<Rams>().0;
+            let robo_damage: f32 = rams
+                .iter()
+                .filter(|(target, _)| *target == robo)
+                .map(|(_, amount)| amount)
+                .sum();
+            let target_damage: f32 = rams
+                .iter()
+                .filter(|(target, _)| *target != robo)
+                .map(|(_, amount)| amount)
+                .sum();
+
+            assert!(robo_damage > 0.0, "{rams:?}");
+            assert!(target_damage > robo_damage, "{rams:?}");
+        }
+
+        /// A weapon definition which fails to load is replaced by the default weapon
+        #[test]
+        fn missing_weapon() {
+            use spacerobo_gun::{Gun, PendingDefinition, definition::WeaponDefinition};
+
+            let mut app: App = app();
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.robo.weapon = "weapons/missing.weapon.ron".to_string();
+            app.insert_resource(configs);
+
+            update(&mut app, 60);
+
+            let (gun, pending) = app
+                .world_mut()
+                .query::<(&Gun, Has<PendingDefinition>)>()
+                .single(app.world())
+                .unwrap();
+            let definition: &WeaponDefinition = app
+                .world()
+                .resource::<Assets<WeaponDefinition>>()
+                .get(&gun.definition)
+                .unwrap();
+
+            assert!(!pending);
+            assert_eq!(definition.name, "Rifle");
+        }
+
+        /// The app exits after the given number of ticks
+        #[test]
+        fn tick_limit() {
+            let mut app: App = App::new();
+            app.add_plugins(HeadlessPlugin::new(Some(3)));
+
+            assert_eq!(app.run(), AppExit::Success);
+        }
+    }
+}
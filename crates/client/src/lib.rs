@@ -1,3 +1,5 @@
 //! Spacerobo
 
 pub mod cli;
+pub mod config;
+pub mod headless;
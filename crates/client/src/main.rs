@@ -1,26 +1,71 @@
 use avian3d::prelude::*;
 use bevy::{
+    log::LogPlugin,
     prelude::*,
     window::{CursorGrabMode, CursorOptions},
 };
 use clap::Parser;
-use spacerobo_client::cli::CLIArgs;
-use spacerobo_commons::{ControllablePlugin, GameMode, configs::GameConfigs};
+use spacerobo_client::{
+    cli::{CLIArgs, Command, ConfigCommand},
+    config,
+    headless::HeadlessPlugin,
+};
+use spacerobo_commons::{
+    ControllablePlugin, GameMode,
+    configs::{GameConfigs, validation::Issue},
+    input::ActionPlugin,
+    replay::{InputPlaybackPlugin, InputRecorderPlugin, InputRecording},
+};
 use spacerobo_shooting_range_plugin::ShootingRangePlugin;
 use spacerobo_title_plugin::TitlePlugin;
+use std::{path::Path, process::ExitCode};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     let args: CLIArgs = CLIArgs::parse();
 
-    let configs: GameConfigs = confy::load_path(args.config_file()).unwrap_or_else(|_| {
-        info!("Running Spacerobo with default GameConfigs...");
-        GameConfigs::default()
-    });
+    if let Some(Command::Config(command)) = args.command() {
+        return Ok(config_command(&args, command));
+    }
+
+    // The logger isn't set up until the app starts, so issues go to stderr
+    let (configs, warnings): (GameConfigs, Vec<Issue>) =
+        match config::load(&args.config_file(), args.overrides()) {
+            Ok(loaded) => loaded,
+            Err(e) => return Ok(failure(e)),
+        };
+    print_warnings(&args.config_file(), &warnings);
 
     debug!("Your GameConfigs: {:?}", configs);
 
-    App::new()
-        .add_plugins((
+    let recording: Option<InputRecording> = match args.replay() {
+        Some(path) => Some(InputRecording::load(&path)?),
+        None => None,
+    };
+
+    let mut app: App = App::new();
+
+    if args.headless() {
+        let initial_state: GameMode = if recording.is_some() {
+            GameMode::Title
+        } else {
+            GameMode::InGame
+        };
+
+        app.add_plugins((
+            LogPlugin::default(),
+            HeadlessPlugin {
+                initial_state,
+                ..HeadlessPlugin::new(args.ticks())
+            },
+        ));
+        info!("Running Spacerobo in headless mode...");
+
+        // A replay starts on the title screen as its recording did
+        if recording.is_some() {
+            app.add_plugins(TitlePlugin);
+        }
+    } else {
+        app.add_plugins((
             DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
                     title: format!("spacerobo {}", env!("CARGO_PKG_VERSION")),
@@ -33,14 +78,97 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }),
                 ..default()
             }),
-            PhysicsPlugins::default(),
             TitlePlugin,
-            ShootingRangePlugin,
-            ControllablePlugin,
         ))
-        .init_state::<GameMode>()
-        .insert_resource(configs)
-        .run();
+        .init_state::<GameMode>();
+    }
+
+    if let Some(path) = args.record() {
+        app.add_plugins(InputRecorderPlugin { path });
+    }
+
+    if let Some(recording) = recording {
+        app.add_plugins(InputPlaybackPlugin { recording });
+    }
+
+    app.add_plugins((
+        PhysicsPlugins::default(),
+        ShootingRangePlugin,
+        ControllablePlugin,
+        ActionPlugin,
+    ))
+    .insert_resource(configs)
+    .run();
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs a `spr config` subcommand, returning the code for the process to exit with
+fn config_command(args: &CLIArgs, command: &ConfigCommand) -> ExitCode {
+    match command {
+        ConfigCommand::Dump => match config::effective(&args.config_file(), args.overrides()) {
+            Ok((configs, warnings)) => {
+                print_warnings(&args.config_file(), &warnings);
+                match configs.to_toml() {
+                    Ok(text) => print!("{text}"),
+                    Err(e) => return failure(e),
+                }
+            }
+            Err(e) => return failure(e),
+        },
+        ConfigCommand::Validate { file } => match config::validate(file, args.overrides()) {
+            Ok(warnings) => {
+                print_warnings(file, &warnings);
+                println!("{}: ok", file.display());
+            }
+            Err(e) => return failure(e),
+        },
+        ConfigCommand::Path => println!("{}", args.config_file().display()),
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn print_warnings(path: &Path, warnings: &[Issue]) {
+    for warning in warnings {
+        eprintln!("warning: {}: {warning}", path.display());
+    }
+}
+
+/// Reports the error, returning the code for the process to exit with
+fn failure(error: impl std::fmt::Display) -> ExitCode {
+    eprintln!("error: {error}");
+    ExitCode::FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config_command;
+    use clap::Parser;
+    use spacerobo_client::cli::{CLIArgs, Command};
+    use std::{path::PathBuf, process::ExitCode};
+
+    /// Runs the `spr` command line's config subcommand
+    fn run(args: &[&str]) -> ExitCode {
+        let args: CLIArgs = CLIArgs::parse_from(["spr"].iter().chain(args));
+        let Some(Command::Config(command)) = args.command() else {
+            panic!("no config subcommand");
+        };
+
+        config_command(&args, command)
+    }
+
+    /// An invalid file fails the process instead of exiting it from the helper
+    #[test]
+    fn exit_code() {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("spacerobo-{}-exit_code.toml", std::process::id()));
+        std::fs::write(&path, "[player.keyboard]\nforward = \"KeyWW\"\n").unwrap();
+
+        let invalid: ExitCode = run(&["config", "validate", path.to_str().unwrap()]);
+        std::fs::remove_file(&path).unwrap();
 
-    Ok(())
+        assert_eq!(invalid, ExitCode::FAILURE);
+        assert_eq!(run(&["config", "path"]), ExitCode::SUCCESS);
+    }
 }
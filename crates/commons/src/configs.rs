@@ -1,14 +1,29 @@
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
+pub mod migration;
 pub mod player;
+pub mod validation;
 
 /// Includes player configuration
-#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct GameConfigs {
+    /// The schema version which the configs are written in. See `migration`.
+    pub version: u32,
+
     pub player: player::Config,
 }
 
+impl std::default::Default for GameConfigs {
+    fn default() -> Self {
+        Self {
+            version: migration::CURRENT_VERSION,
+            player: player::Config::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     mod game_configs {
@@ -0,0 +1,234 @@
+//! # Config schema versions & migrations
+//!
+//! Every config file has a `version`. Files without it are version 0, written before versioning.
+//! Older files are migrated step by step in their TOML form, and then missing keys are filled by the defaults.
+//! Keys which the configs don't have are rejected, so a typo isn't silently replaced by the default.
+//!
+//! - 0: Unversioned
+//! - 1: Adds `version`. The keys of version 0 are unchanged, and keys added since are optional
+
+use super::GameConfigs;
+use thiserror::Error;
+use toml::{Table, Value};
+
+/// The schema version which this build writes
+pub const CURRENT_VERSION: u32 = 1;
+
+/// A migration from a version to the next one
+type Migration = fn(&mut Table);
+
+/// `MIGRATIONS[n]` migrates version `n` to `n + 1`
+const MIGRATIONS: [Migration; CURRENT_VERSION as usize] = [v0_to_v1];
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("{0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("version must be a non-negative integer")]
+    InvalidVersion,
+
+    #[error("version {0} is newer than this spacerobo supports ({CURRENT_VERSION})")]
+    Newer(u32),
+
+    #[error("unknown key {}", .0.join(", "))]
+    UnknownKeys(Vec<String>),
+
+    #[error("{0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+impl GameConfigs {
+    /// Parses a config file, migrating it from an older version.
+    /// Returns the configs with the version which the file was written in.
+    pub fn from_toml(text: &str) -> Result<(Self, u32), MigrationError> {
+        let table: Table = toml::from_str(text)?;
+
+        let version: u32 = match table.get("version") {
+            None => 0,
+            Some(Value::Integer(version)) => {
+                u32::try_from(*version).map_err(|_| MigrationError::InvalidVersion)?
+            }
+            Some(_) => return Err(MigrationError::InvalidVersion),
+        };
+        if version > CURRENT_VERSION {
+            return Err(MigrationError::Newer(version));
+        }
+
+        let mut migrated: Table = table.clone();
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut migrated);
+        }
+
+        // Parsing the text itself keeps line numbers in errors, if nothing has been migrated
+        let mut configs: GameConfigs = if migrated == table {
+            toml::from_str(text)?
+        } else {
+            Value::Table(migrated.clone()).try_into()?
+        };
+
+        // Unknown keys are dropped by deserialization, so every key must survive a round trip
+        let written: Table = Table::try_from(&configs)?;
+        let unknown: Vec<String> = unknown_keys(&migrated, &written, "");
+        if !unknown.is_empty() {
+            return Err(MigrationError::UnknownKeys(unknown));
+        }
+
+        configs.version = CURRENT_VERSION;
+
+        Ok((configs, version))
+    }
+
+    /// Writes the configs as a config file of the current version
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(&GameConfigs {
+            version: CURRENT_VERSION,
+            ..self.clone()
+        })
+    }
+}
+
+/// Key paths in the table which the known table doesn't have
+fn unknown_keys(table: &Table, known: &Table, prefix: &str) -> Vec<String> {
+    table
+        .iter()
+        .flat_map(|(key, value)| {
+            let path: String = format!("{prefix}{key}");
+            match (value, known.get(key)) {
+                (_, None) => vec![path],
+                (Value::Table(table), Some(Value::Table(known))) => {
+                    unknown_keys(table, known, &format!("{path}."))
+                }
+                _ => Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Version 1 only adds `version`, which is set after the migrations, so the keys are kept as they are
+fn v0_to_v1(_table: &mut Table) {}
+
+#[cfg(test)]
+mod tests {
+    mod from_toml {
+        use crate::configs::{
+            GameConfigs,
+            migration::{CURRENT_VERSION, MigrationError},
+        };
+        use bevy::prelude::*;
+
+        /// A file which the unversioned spacerobo wrote, with every key which it had and a few values changed
+        const V0: &str = r#"
+[player.keyboard]
+forward = "KeyW"
+back = "KeyS"
+left = "KeyA"
+right = "KeyD"
+dash = "ShiftLeft"
+hover = "ControlLeft"
+toggle_firemode = "KeyG"
+quit = "Escape"
+respawn = "Space"
+
+[player.mouse]
+x_reverse = true
+y_reverse = false
+
+[player.robo.thruster.force]
+accelerate = 0.5
+dash = 3.0
+pitch = 1.0
+yaw = 1.0
+roll = 1.0
+"#;
+
+        /// A version 1 file, which only has the keys changed from the defaults
+        const V1: &str = r#"
+version = 1
+
+[player.keyboard]
+toggle_firemode = "KeyG"
+
+[player.mouse]
+x_reverse = true
+
+[player.robo.thruster.force]
+accelerate = 0.5
+"#;
+
+        /// The configs which both samples describe
+        fn expected() -> GameConfigs {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.keyboard.toggle_firemode = KeyCode::KeyG;
+            configs.player.mouse.x_reverse = true;
+            configs.player.robo.thruster.force.accelerate = 0.5;
+
+            configs
+        }
+
+        /// Loads the sample, and checks that writing & loading it again keeps the configs
+        fn round_trip(text: &str, version: u32) {
+            let (configs, from) = GameConfigs::from_toml(text).unwrap();
+            assert_eq!(from, version);
+            assert_eq!(configs, expected());
+
+            let written: String = configs.to_toml().unwrap();
+            let (reloaded, from) = GameConfigs::from_toml(&written).unwrap();
+            assert_eq!(from, CURRENT_VERSION);
+            assert_eq!(reloaded, configs);
+        }
+
+        #[test]
+        fn v0() {
+            round_trip(V0, 0);
+        }
+
+        #[test]
+        fn v1() {
+            round_trip(V1, 1);
+        }
+
+        /// An empty file is the defaults
+        #[test]
+        fn empty() {
+            let (configs, _) = GameConfigs::from_toml("").unwrap();
+
+            assert_eq!(configs, GameConfigs::default());
+        }
+
+        /// A file from a newer build is rejected rather than misread
+        #[test]
+        fn newer() {
+            let result = GameConfigs::from_toml("version = 99");
+
+            assert!(matches!(result, Err(MigrationError::Newer(99))));
+        }
+
+        /// Misspelled keys are rejected with their key paths, in both old & current files
+        #[test]
+        fn unknown_keys() {
+            let error: MigrationError = GameConfigs::from_toml(
+                "version = 1\n\n[player.keyboard]\nfowrard = \"KeyQ\"\n\n[player.robo.thruster.force]\naccelerat = 1.0\n",
+            )
+            .unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "unknown key player.keyboard.fowrard, player.robo.thruster.force.accelerat"
+            );
+
+            let error: MigrationError =
+                GameConfigs::from_toml("[player.mouse]\nx_revrese = true\n").unwrap_err();
+            assert_eq!(error.to_string(), "unknown key player.mouse.x_revrese");
+        }
+
+        /// Errors in files which need no migration keep the line
+        #[test]
+        fn line_number() {
+            let error: MigrationError =
+                GameConfigs::from_toml("version = 1\n\n[player.mouse]\nx_reverse = 1\n")
+                    .unwrap_err();
+
+            assert!(error.to_string().contains("line 4"), "{error}");
+        }
+    }
+}
@@ -1,35 +1,197 @@
 //! Player's Configuration
 
+use crate::{
+    Armor, FlightAssist, Resistances,
+    input::{Action, Binding},
+};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Configuration struct
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
 pub struct Config {
     pub keyboard: KeyboardConfig,
     pub mouse: MouseConfig,
+    pub gamepad: GamepadConfig,
+
     pub robo: RoboConfig,
+
+    /// Bindings of actions. An action listed here is bound only to these inputs, instead of its defaults.
+    pub actions: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Config {
+    /// Inputs which the action is bound to.
+    /// Unless `actions` lists it, they are its key in `keyboard` and its default mouse & gamepad bindings.
+    pub fn bindings(&self, action: Action) -> Vec<Binding> {
+        if let Some(bindings) = self.actions.get(&action) {
+            return bindings.clone();
+        }
+
+        self.keyboard
+            .key(action)
+            .map(Binding::Key)
+            .into_iter()
+            .chain(action.default_bindings())
+            .collect()
+    }
 }
 
 // Configurations about robo
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct RoboConfig {
     pub thruster: ThrusterConfig,
+
+    /// An asset path of the weapon definition which the robo is armed with
+    pub weapon: String,
+
+    /// The robo has no shield if it is `None`
+    pub shield: Option<ShieldConfig>,
+
+    /// The robo has no armor if it is `None`
+    pub armor: Option<Armor>,
+
+    /// Per damage kind resistances
+    pub resistances: Resistances,
+
+    /// Thruster fuel
+    pub fuel: FuelConfig,
+
+    /// Flight assist mode when the robo is spawned
+    pub flight_assist: FlightAssist,
+}
+
+impl std::default::Default for RoboConfig {
+    fn default() -> Self {
+        Self {
+            thruster: ThrusterConfig::default(),
+            weapon: "weapons/rifle.weapon.ron".to_string(),
+            shield: None,
+            armor: None,
+            resistances: Resistances::default(),
+            fuel: FuelConfig::default(),
+            flight_assist: FlightAssist::default(),
+        }
+    }
+}
+
+// Configurations about thruster fuel
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FuelConfig {
+    pub capacity: f32,
+
+    /// Regenerated amount per second while the pilot commands no thrust
+    pub regeneration: f32,
+
+    /// Burnt amount per second by a unit of thrust, so dashing burns more
+    pub consumption: f32,
+}
+
+impl std::default::Default for FuelConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 100.0,
+            regeneration: 20.0,
+            consumption: 10.0,
+        }
+    }
+}
+
+// Configurations about shield
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ShieldConfig {
+    pub capacity: f32,
+
+    /// Regenerated amount per second
+    pub regeneration: f32,
+
+    /// Seconds from the last hit until the regeneration starts
+    pub delay: f32,
 }
 
 // Configurations about thrusters
-#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct ThrusterConfig {
     pub force: ForceConfig,
+
+    /// The speed which the assisted flight mode commands with the movement keys
+    pub assist_speed: f32,
+
+    /// Thrusters can't speed the robo up over this speed
+    pub max_speed: f32,
+
+    /// Thrusters & the mouse can't spin the robo faster than this, in radians per second
+    pub max_angular_rate: f32,
+
+    /// Linear thrust limits on each local axis
+    pub axis_limits: AxisLimits,
+}
+
+impl std::default::Default for ThrusterConfig {
+    fn default() -> Self {
+        Self {
+            force: ForceConfig::default(),
+            assist_speed: 50.0,
+            max_speed: 200.0,
+            max_angular_rate: 10.0,
+            axis_limits: AxisLimits::default(),
+        }
+    }
+}
+
+// Linear thrust limits on each local axis, in velocity change per 1/60 second
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AxisLimits {
+    /// Left & right
+    pub lateral: f32,
+
+    /// Up & down
+    pub vertical: f32,
+
+    /// Forward & back
+    pub longitudinal: f32,
+}
+
+impl AxisLimits {
+    /// Limits as a local space vector
+    pub fn to_vec3(&self) -> Vec3 {
+        Vec3::new(self.lateral, self.vertical, self.longitudinal)
+    }
+}
+
+impl std::default::Default for AxisLimits {
+    fn default() -> Self {
+        Self {
+            lateral: 5.0,
+            vertical: 5.0,
+            longitudinal: 5.0,
+        }
+    }
 }
 
 // Configuration about force by thrusters
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct ForceConfig {
     // Keyboard
     pub accelerate: f32,
     pub dash: f32,
 
+    /// Up & down translation
+    pub vertical: f32,
+
+    /// Roll by the roll keys
+    pub keyboard_roll: f32,
+
+    /// Angular thrust to stop the spin, scaled by pitch, yaw & roll for each axis
+    pub kill_rotation: f32,
+
     // Mouse
     pub pitch: f32,
     pub yaw: f32,
@@ -41,6 +203,9 @@ impl std::default::Default for ForceConfig {
         Self {
             accelerate: 0.7,
             dash: 3.0,
+            vertical: 0.7,
+            keyboard_roll: 0.05,
+            kill_rotation: 0.05,
             pitch: 1.0,
             yaw: 1.0,
             roll: 1.0,
@@ -50,6 +215,7 @@ impl std::default::Default for ForceConfig {
 
 /// Keyboard Configurations. This structure usually contains keymappings.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct KeyboardConfig {
     // Movements
     pub forward: KeyCode,
@@ -57,14 +223,26 @@ pub struct KeyboardConfig {
     pub left: KeyCode,
     pub right: KeyCode,
 
+    pub up: KeyCode,
+    pub down: KeyCode,
+
+    pub roll_left: KeyCode,
+    pub roll_right: KeyCode,
+
     pub dash: KeyCode,
 
     // Hovering
     pub hover: KeyCode,
 
+    // Flight assist
+    pub toggle_flight_assist: KeyCode,
+    pub kill_rotation: KeyCode,
+
     // Gun
     pub toggle_firemode: KeyCode,
 
+    pub reload: KeyCode,
+
     // Game quit key
     pub quit: KeyCode,
 
@@ -72,6 +250,46 @@ pub struct KeyboardConfig {
     pub respawn: KeyCode,
 }
 
+impl KeyboardConfig {
+    /// The key of the action, if this config has a field for it
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.fields()
+            .into_iter()
+            .find(|(a, _, _)| *a == action)
+            .map(|(_, _, key)| key)
+    }
+
+    /// Every key with its action and its field name
+    pub fn fields(&self) -> [(Action, &'static str, KeyCode); 16] {
+        [
+            (Action::Forward, "forward", self.forward),
+            (Action::Back, "back", self.back),
+            (Action::Left, "left", self.left),
+            (Action::Right, "right", self.right),
+            (Action::Up, "up", self.up),
+            (Action::Down, "down", self.down),
+            (Action::RollLeft, "roll_left", self.roll_left),
+            (Action::RollRight, "roll_right", self.roll_right),
+            (Action::Dash, "dash", self.dash),
+            (Action::Hover, "hover", self.hover),
+            (
+                Action::ToggleFlightAssist,
+                "toggle_flight_assist",
+                self.toggle_flight_assist,
+            ),
+            (Action::KillRotation, "kill_rotation", self.kill_rotation),
+            (
+                Action::ToggleFireMode,
+                "toggle_firemode",
+                self.toggle_firemode,
+            ),
+            (Action::Reload, "reload", self.reload),
+            (Action::Quit, "quit", self.quit),
+            (Action::Respawn, "respawn", self.respawn),
+        ]
+    }
+}
+
 impl std::default::Default for KeyboardConfig {
     fn default() -> Self {
         Self {
@@ -80,11 +298,21 @@ impl std::default::Default for KeyboardConfig {
             left: KeyCode::KeyA,
             right: KeyCode::KeyD,
 
+            up: KeyCode::KeyF,
+            down: KeyCode::KeyC,
+
+            roll_left: KeyCode::KeyE,
+            roll_right: KeyCode::KeyQ,
+
             dash: KeyCode::ShiftLeft,
 
             hover: KeyCode::ControlLeft,
 
+            toggle_flight_assist: KeyCode::KeyV,
+            kill_rotation: KeyCode::KeyX,
+
             toggle_firemode: KeyCode::KeyT,
+            reload: KeyCode::KeyR,
 
             quit: KeyCode::Escape,
 
@@ -95,7 +323,158 @@ impl std::default::Default for KeyboardConfig {
 
 /// Mouse Configurations
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
 pub struct MouseConfig {
     pub x_reverse: bool,
     pub y_reverse: bool,
+
+    /// Feel of the normal camera
+    pub normal: MouseFeelConfig,
+
+    /// Feel of the flight camera, while the FlightCamera action is held
+    pub flight: MouseFeelConfig,
+}
+
+/// Gamepad Configurations
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// Stick values up to this are ignored, and the rest is rescaled from 0.0 to 1.0
+    pub deadzone: f32,
+
+    /// Reverses the horizontal axis of the pitch & yaw actions, which is the right stick by default
+    pub x_reverse: bool,
+
+    /// Reverses the vertical axis of the pitch & yaw actions
+    pub y_reverse: bool,
+
+    /// Angular thrust of the pitch & yaw actions at full tilt, as a ratio of the pitch & yaw forces
+    pub look_sensitivity: f32,
+}
+
+impl std::default::Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            x_reverse: false,
+            y_reverse: false,
+            look_sensitivity: 0.05,
+        }
+    }
+}
+
+/// How mouse motion is turned into rotation
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct MouseFeelConfig {
+    /// Rotation per pixel of horizontal motion
+    pub sensitivity_x: f32,
+
+    /// Rotation per pixel of vertical motion
+    pub sensitivity_y: f32,
+
+    /// The number of frames which motion is averaged over. `1` doesn't smooth.
+    pub smoothing: usize,
+
+    /// Motion up to this many pixels per frame is ignored
+    pub deadzone: f32,
+
+    /// Sensitivity grows by this ratio per pixel per frame, so fast flicks turn further. `0.0` is linear.
+    pub acceleration: f32,
+}
+
+impl std::default::Default for MouseFeelConfig {
+    fn default() -> Self {
+        Self {
+            sensitivity_x: 0.01,
+            sensitivity_y: 0.01,
+            smoothing: 1,
+            deadzone: 0.0,
+            acceleration: 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod keyboard_config {
+        use crate::configs::player::KeyboardConfig;
+        use bevy::prelude::*;
+
+        /// Config files written before the six-degrees-of-freedom keys still load
+        #[test]
+        fn without_six_dof_keys() {
+            let config: KeyboardConfig = toml::from_str(
+                r#"
+forward = "KeyW"
+back = "KeyS"
+left = "KeyA"
+right = "KeyD"
+dash = "ShiftLeft"
+hover = "ControlLeft"
+toggle_firemode = "KeyT"
+quit = "Escape"
+respawn = "Space"
+"#,
+            )
+            .unwrap();
+
+            assert_eq!(config, KeyboardConfig::default());
+            assert_eq!(config.roll_left, KeyCode::KeyE);
+        }
+    }
+
+    mod mouse_config {
+        use crate::configs::player::{MouseConfig, MouseFeelConfig};
+
+        /// Config files without the feel settings still load
+        #[test]
+        fn without_feel() {
+            let config: MouseConfig = toml::from_str(
+                r#"
+x_reverse = true
+y_reverse = false
+"#,
+            )
+            .unwrap();
+
+            assert!(config.x_reverse);
+            assert_eq!(config.normal, MouseFeelConfig::default());
+            assert_eq!(config.flight, MouseFeelConfig::default());
+        }
+    }
+
+    mod gamepad_config {
+        use crate::configs::player::GamepadConfig;
+
+        /// Missing fields fall back to their defaults
+        #[test]
+        fn partial() {
+            let config: GamepadConfig = toml::from_str("y_reverse = true").unwrap();
+
+            assert!(config.y_reverse);
+            assert_eq!(config.deadzone, GamepadConfig::default().deadzone);
+        }
+    }
+
+    mod force_config {
+        use crate::configs::player::ForceConfig;
+
+        /// Config files without the vertical & keyboard roll forces still load
+        #[test]
+        fn without_six_dof_forces() {
+            let config: ForceConfig = toml::from_str(
+                r#"
+accelerate = 0.7
+dash = 3.0
+pitch = 1.0
+yaw = 1.0
+roll = 1.0
+"#,
+            )
+            .unwrap();
+
+            assert_eq!(config, ForceConfig::default());
+        }
+    }
 }
@@ -0,0 +1,366 @@
+//! # Validation of GameConfigs
+//!
+//! Finds values which deserialize fine but can't work, such as two actions bound to the same key or a negative force.
+
+use super::GameConfigs;
+use crate::input::{Action, Binding};
+use bevy::platform::collections::HashMap;
+use thiserror::Error;
+
+/// How serious an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The game works, but probably not as the player expects
+    Warning,
+
+    /// The game can't work with the value
+    Error,
+}
+
+/// A problem in GameConfigs, at a key path such as `player.robo.fuel.capacity`
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{path}: {reason}")]
+pub struct Issue {
+    pub severity: Severity,
+    pub path: String,
+    pub reason: String,
+}
+
+impl Issue {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Collects issues while walking through GameConfigs
+#[derive(Default)]
+struct Validator {
+    issues: Vec<Issue>,
+}
+
+impl Validator {
+    fn push(&mut self, severity: Severity, path: impl Into<String>, reason: impl Into<String>) {
+        self.issues.push(Issue {
+            severity,
+            path: path.into(),
+            reason: reason.into(),
+        });
+    }
+
+    fn error(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.push(Severity::Error, path, reason);
+    }
+
+    fn warn(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.push(Severity::Warning, path, reason);
+    }
+
+    /// The value must be a finite number, at least `min`
+    fn at_least(&mut self, path: &str, value: f32, min: f32) -> bool {
+        if !value.is_finite() {
+            self.error(path, format!("{value} is not a finite number"));
+            false
+        } else if value < min {
+            self.error(path, format!("{value} is less than {min}"));
+            false
+        } else {
+            true
+        }
+    }
+
+    /// The value must be a finite number, greater than zero
+    fn positive(&mut self, path: &str, value: f32) {
+        if self.at_least(path, value, 0.) && value == 0. {
+            self.error(path, "must be greater than 0");
+        }
+    }
+
+    /// The value must be a finite number from `min` to `max`
+    fn within(&mut self, path: &str, value: f32, min: f32, max: f32) {
+        if self.at_least(path, value, min) && value > max {
+            self.error(path, format!("{value} is greater than {max}"));
+        }
+    }
+}
+
+impl GameConfigs {
+    /// Finds issues such as duplicate bindings and out-of-range forces
+    pub fn validate(&self) -> Vec<Issue> {
+        let mut validator: Validator = Validator::default();
+
+        self.validate_bindings(&mut validator);
+        self.validate_robo(&mut validator);
+        self.validate_devices(&mut validator);
+
+        validator.issues
+    }
+
+    /// Two actions read on the same screen can't share an input
+    fn validate_bindings(&self, validator: &mut Validator) {
+        let player = &self.player;
+
+        // Where a binding is written, to point the player at it
+        let path = |action: Action, binding: &Binding| -> String {
+            if player.actions.contains_key(&action) {
+                return format!("player.actions.{}", action.name());
+            }
+
+            player
+                .keyboard
+                .fields()
+                .into_iter()
+                .find(|(a, _, key)| *a == action && *binding == Binding::Key(*key))
+                .map_or(
+                    format!("player.actions.{} (default)", action.name()),
+                    |(_, field, _)| format!("player.keyboard.{field}"),
+                )
+        };
+
+        for action in player.actions.keys() {
+            if player.actions[action].is_empty() {
+                validator.warn(
+                    format!("player.actions.{}", action.name()),
+                    "is bound to nothing",
+                );
+            }
+        }
+
+        type Screen = (&'static str, fn(Action) -> bool);
+        let screens: [Screen; 2] = [
+            ("the title screen", Action::on_title),
+            ("the shooting range", Action::in_game),
+        ];
+
+        for (screen, filter) in screens {
+            let mut bound: HashMap<Binding, Action> = HashMap::new();
+
+            for action in Action::ALL.into_iter().filter(|a| filter(*a)) {
+                for binding in player.bindings(action) {
+                    match bound.get(&binding) {
+                        Some(other) if *other != action => validator.error(
+                            path(action, &binding),
+                            format!("{binding} is also bound to {} on {screen}", other.name()),
+                        ),
+                        Some(_) => {}
+                        None => {
+                            bound.insert(binding, action);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_robo(&self, validator: &mut Validator) {
+        let robo = &self.player.robo;
+        let thruster = &robo.thruster;
+        let force = &thruster.force;
+
+        for (name, value) in [
+            ("accelerate", force.accelerate),
+            ("dash", force.dash),
+            ("vertical", force.vertical),
+            ("keyboard_roll", force.keyboard_roll),
+            ("kill_rotation", force.kill_rotation),
+            ("pitch", force.pitch),
+            ("yaw", force.yaw),
+            ("roll", force.roll),
+        ] {
+            validator.at_least(&format!("player.robo.thruster.force.{name}"), value, 0.);
+        }
+
+        validator.positive("player.robo.thruster.assist_speed", thruster.assist_speed);
+        validator.positive("player.robo.thruster.max_speed", thruster.max_speed);
+        validator.positive(
+            "player.robo.thruster.max_angular_rate",
+            thruster.max_angular_rate,
+        );
+
+        let limits = &thruster.axis_limits;
+        for (name, value) in [
+            ("lateral", limits.lateral),
+            ("vertical", limits.vertical),
+            ("longitudinal", limits.longitudinal),
+        ] {
+            validator.positive(&format!("player.robo.thruster.axis_limits.{name}"), value);
+        }
+
+        if force.accelerate + force.dash > limits.longitudinal {
+            validator.warn(
+                "player.robo.thruster.force.dash",
+                format!(
+                    "accelerate + dash ({}) is cut to axis_limits.longitudinal ({})",
+                    force.accelerate + force.dash,
+                    limits.longitudinal
+                ),
+            );
+        }
+
+        validator.positive("player.robo.fuel.capacity", robo.fuel.capacity);
+        validator.at_least("player.robo.fuel.regeneration", robo.fuel.regeneration, 0.);
+        validator.at_least("player.robo.fuel.consumption", robo.fuel.consumption, 0.);
+
+        if let Some(shield) = &robo.shield {
+            validator.positive("player.robo.shield.capacity", shield.capacity);
+            validator.at_least("player.robo.shield.regeneration", shield.regeneration, 0.);
+            validator.at_least("player.robo.shield.delay", shield.delay, 0.);
+        }
+
+        if let Some(armor) = &robo.armor {
+            for (name, reduction) in [
+                ("kinetic", armor.kinetic),
+                ("energy", armor.energy),
+                ("explosive", armor.explosive),
+                ("collision", armor.collision),
+                ("melee", armor.melee),
+            ] {
+                let path: String = format!("player.robo.armor.{name}");
+                validator.at_least(&format!("{path}.flat"), reduction.flat, 0.);
+                validator.within(&format!("{path}.percent"), reduction.percent, 0., 1.);
+            }
+        }
+
+        let resistances = &robo.resistances;
+        for (name, value) in [
+            ("kinetic", resistances.kinetic),
+            ("energy", resistances.energy),
+            ("explosive", resistances.explosive),
+            ("collision", resistances.collision),
+            ("melee", resistances.melee),
+        ] {
+            let path: String = format!("player.robo.resistances.{name}");
+            if validator.at_least(&path, value, f32::MIN) && value > 1. {
+                validator.warn(path, format!("{value} works as 1.0, immune"));
+            }
+        }
+    }
+
+    fn validate_devices(&self, validator: &mut Validator) {
+        for (name, feel) in [
+            ("normal", &self.player.mouse.normal),
+            ("flight", &self.player.mouse.flight),
+        ] {
+            let path = |field: &str| format!("player.mouse.{name}.{field}");
+
+            validator.at_least(&path("sensitivity_x"), feel.sensitivity_x, f32::MIN);
+            validator.at_least(&path("sensitivity_y"), feel.sensitivity_y, f32::MIN);
+            validator.at_least(&path("deadzone"), feel.deadzone, 0.);
+            validator.at_least(&path("acceleration"), feel.acceleration, 0.);
+
+            if feel.smoothing == 0 {
+                validator.warn(path("smoothing"), "0 works as 1, which doesn't smooth");
+            }
+        }
+
+        let gamepad = &self.player.gamepad;
+        validator.within("player.gamepad.deadzone", gamepad.deadzone, 0., 0.99);
+        validator.at_least(
+            "player.gamepad.look_sensitivity",
+            gamepad.look_sensitivity,
+            0.,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod validate {
+        use crate::{
+            configs::{
+                GameConfigs,
+                validation::{Issue, Severity},
+            },
+            input::{Action, Binding},
+        };
+        use bevy::prelude::*;
+
+        fn errors(configs: &GameConfigs) -> Vec<Issue> {
+            configs
+                .validate()
+                .into_iter()
+                .filter(Issue::is_error)
+                .collect()
+        }
+
+        /// The default configs have no issue
+        #[test]
+        fn default() {
+            assert_eq!(GameConfigs::default().validate(), Vec::new());
+        }
+
+        /// Binding forward & dash to the same key is reported at the later key
+        #[test]
+        fn duplicate_keys() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.keyboard.dash = KeyCode::KeyW;
+
+            let errors: Vec<Issue> = errors(&configs);
+
+            assert_eq!(errors.len(), 1, "{errors:?}");
+            assert_eq!(errors[0].path, "player.keyboard.dash");
+            assert_eq!(
+                errors[0].reason,
+                "KeyW is also bound to forward on the shooting range"
+            );
+        }
+
+        /// Configured actions conflict with keys & default bindings too
+        #[test]
+        fn duplicate_actions() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs
+                .player
+                .actions
+                .insert(Action::Reload, vec![Binding::Mouse(MouseButton::Left)]);
+
+            let errors: Vec<Issue> = errors(&configs);
+
+            assert_eq!(errors.len(), 1, "{errors:?}");
+            assert_eq!(errors[0].path, "player.actions.reload");
+        }
+
+        /// Actions on different screens may share an input, as start & respawn do
+        #[test]
+        fn different_screens() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.keyboard.respawn = KeyCode::Enter;
+            configs
+                .player
+                .actions
+                .insert(Action::Start, vec![Binding::Key(KeyCode::Enter)]);
+
+            assert_eq!(errors(&configs), Vec::new());
+        }
+
+        /// Negative & non-finite forces are errors
+        #[test]
+        fn out_of_range_forces() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.robo.thruster.force.accelerate = -0.7;
+            configs.player.robo.thruster.force.pitch = f32::NAN;
+
+            let paths: Vec<String> = errors(&configs).into_iter().map(|i| i.path).collect();
+
+            assert_eq!(
+                paths,
+                vec![
+                    "player.robo.thruster.force.accelerate",
+                    "player.robo.thruster.force.pitch"
+                ]
+            );
+        }
+
+        /// Values which still work are warnings
+        #[test]
+        fn warnings() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.robo.resistances.energy = 1.5;
+            configs.player.mouse.flight.smoothing = 0;
+
+            let issues: Vec<Issue> = configs.validate();
+
+            assert_eq!(issues.len(), 2, "{issues:?}");
+            assert!(issues.iter().all(|i| i.severity == Severity::Warning));
+        }
+    }
+}
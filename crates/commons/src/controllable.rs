@@ -2,16 +2,97 @@
 
 use bevy::prelude::*;
 
-mod keyboard;
+mod action;
 mod mouse;
+mod thruster;
+
+pub use thruster::{FlightAssist, ThrustInput};
 
 #[derive(Component)]
+#[require(ThrustInput, FlightAssist)]
 pub struct Controllable;
+
+/// Thruster fuel of a controllable robo, which is burnt by thrust and regenerates while the pilot commands none
+#[derive(Debug, Component, Clone, PartialEq)]
+pub struct Fuel {
+    pub rest: f32,
+    pub maximum: f32,
+
+    /// Regenerated amount per second while the pilot commands no thrust
+    pub regeneration: f32,
+
+    /// Burnt amount per second by a unit of thrust
+    pub consumption: f32,
+}
+
+impl Fuel {
+    pub fn new(maximum: f32, regeneration: f32, consumption: f32) -> Self {
+        Self {
+            rest: maximum,
+            maximum,
+            regeneration,
+            consumption,
+        }
+    }
+
+    /// Burns fuel for the thrust, and returns the ratio of the thrust which the fuel could afford
+    pub fn burn(&mut self, thrust: f32) -> f32 {
+        let cost: f32 = thrust * self.consumption;
+        if cost <= 0. {
+            return 1.;
+        }
+
+        let burnt: f32 = cost.min(self.rest);
+        self.rest -= burnt;
+
+        burnt / cost
+    }
+
+    pub fn regenerate(&mut self, delta: f32) {
+        self.rest = (self.rest + self.regeneration * delta).min(self.maximum);
+    }
+}
 pub struct ControllablePlugin;
 
 impl Plugin for ControllablePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, keyboard::update_system);
+        app.add_systems(Update, action::update_system);
         app.add_systems(Update, mouse::update_system);
+        app.add_systems(FixedUpdate, thruster::apply_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Fuel's unit tests
+    mod fuel {
+        use crate::Fuel;
+
+        /// Thrust is cut down to what the rest of the fuel affords
+        #[test]
+        fn burn() {
+            let mut fuel: Fuel = Fuel::new(10., 5., 2.);
+
+            assert_eq!(fuel.burn(4.), 1.);
+            assert_eq!(fuel.rest, 2.);
+
+            assert_eq!(fuel.burn(2.), 0.5);
+            assert_eq!(fuel.rest, 0.);
+
+            assert_eq!(fuel.burn(1.), 0.);
+        }
+
+        /// Fuel regenerates up to its maximum
+        #[test]
+        fn regenerate() {
+            let mut fuel: Fuel = Fuel::new(10., 5., 2.);
+            fuel.burn(4.);
+
+            fuel.regenerate(1.);
+            assert_eq!(fuel.rest, 7.);
+
+            fuel.regenerate(1.);
+            assert_eq!(fuel.rest, 10.);
+        }
     }
 }
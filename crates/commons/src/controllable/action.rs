@@ -0,0 +1,93 @@
+use super::{Controllable, FlightAssist, ThrustInput};
+use crate::{
+    GameMode,
+    configs::GameConfigs,
+    input::{Action, ActionState},
+};
+use bevy::prelude::*;
+
+pub fn update_system(
+    mut gamemode: ResMut<NextState<GameMode>>,
+    mut query: Query<(&Transform, &mut ThrustInput, &mut FlightAssist), With<Controllable>>,
+    actions: Res<ActionState>,
+    game_configs: Res<GameConfigs>,
+) {
+    let force = &game_configs.player.robo.thruster.force;
+
+    for (transform, mut input, mut flight_assist) in query.iter_mut() {
+        // Hovering
+        input.hover = actions.pressed(Action::Hover);
+
+        // Flight assist
+        if actions.just_pressed(Action::ToggleFlightAssist) {
+            flight_assist.toggle();
+            info!("Flight assist: {:?}", *flight_assist);
+        }
+        input.kill_rotation = actions.pressed(Action::KillRotation);
+
+        // Exit spacerobo
+        if actions.just_pressed(Action::Quit) {
+            gamemode.set(GameMode::Title);
+        }
+
+        // Thrust requested by the actions, scaled by analog inputs such as sticks & triggers
+        let mut thrust: Vec3 = Vec3::ZERO;
+        let mut torque: Vec3 = Vec3::ZERO;
+
+        let rotation: Quat = transform.rotation;
+
+        // Accelerate & dash, along each local axis
+        {
+            let translations: [(Action, Vec3, f32); 6] = [
+                (Action::Forward, Vec3::NEG_Z, force.accelerate),
+                (Action::Back, Vec3::Z, force.accelerate),
+                (Action::Left, Vec3::NEG_X, force.accelerate),
+                (Action::Right, Vec3::X, force.accelerate),
+                (Action::Up, Vec3::Y, force.vertical),
+                (Action::Down, Vec3::NEG_Y, force.vertical),
+            ];
+
+            for (action, direction, force_value) in translations {
+                let amount: f32 = actions.value(action);
+                if amount <= 0. {
+                    continue;
+                }
+
+                let value: f32 = force_value + force.dash * actions.value(Action::Dash);
+
+                thrust += rotation * direction * value * amount;
+            }
+        }
+
+        // Roll
+        {
+            let rolls: [(Action, Vec3); 2] = [
+                (Action::RollLeft, Vec3::Z),
+                (Action::RollRight, Vec3::NEG_Z),
+            ];
+
+            for (action, axis) in rolls {
+                torque += rotation * axis * force.keyboard_roll * actions.value(action);
+            }
+        }
+
+        // Pitch & yaw, scaled by how far the stick is pushed
+        {
+            let gamepad = &game_configs.player.gamepad;
+            let reverse = |reverse: bool| if reverse { -1. } else { 1. };
+
+            let pitch: f32 = (actions.value(Action::PitchUp) - actions.value(Action::PitchDown))
+                * reverse(gamepad.y_reverse);
+            let yaw: f32 = (actions.value(Action::YawLeft) - actions.value(Action::YawRight))
+                * reverse(gamepad.x_reverse);
+
+            torque += rotation
+                * Vec3::new(pitch * force.pitch, yaw * force.yaw, 0.)
+                * gamepad.look_sensitivity;
+        }
+
+        // Applied in FixedUpdate
+        input.linear = thrust;
+        input.torque = torque;
+    }
+}
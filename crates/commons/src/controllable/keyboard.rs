@@ -1,174 +0,0 @@
-use super::Controllable;
-use crate::{GameMode, configs::GameConfigs};
-use avian3d::prelude::*;
-use bevy::prelude::*;
-
-pub fn update_system(
-    mut gamemode: ResMut<NextState<GameMode>>,
-    mut query: Query<(&Transform, &mut AngularVelocity, &mut LinearVelocity), With<Controllable>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    game_configs: Res<GameConfigs>,
-) {
-    for (transform, mut angular, mut linear) in query.iter_mut() {
-        // Hovering
-        if keyboard.pressed(game_configs.player.keyboard.hover) {
-            angular.0 *= Vec3::new(0.7, 0.7, 0.7);
-            linear.0 *= Vec3::new(0.7, 0.7, 0.7);
-        }
-
-        // Exit spacerobo
-        if keyboard.just_pressed(game_configs.player.keyboard.quit) {
-            gamemode.set(GameMode::Title);
-        }
-
-        // Accelerate
-        {
-            if keyboard.pressed(game_configs.player.keyboard.forward) {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.accelerate;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::NEG_Z;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.left) {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.accelerate;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::NEG_X;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.back) {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.accelerate;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::Z;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.right) {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.accelerate;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::X;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-        }
-
-        // Dash
-        {
-            if keyboard.pressed(game_configs.player.keyboard.dash)
-                && keyboard.pressed(game_configs.player.keyboard.forward)
-            {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.dash;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::NEG_Z;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.dash)
-                && keyboard.pressed(game_configs.player.keyboard.left)
-            {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.dash;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::NEG_X;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.dash)
-                && keyboard.pressed(game_configs.player.keyboard.back)
-            {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.dash;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::Z;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-
-            if keyboard.pressed(game_configs.player.keyboard.dash)
-                && keyboard.pressed(game_configs.player.keyboard.right)
-            {
-                let mut velocity: Vec3 = Vec3::ZERO;
-                let force: f32 = game_configs.player.robo.thruster.force.dash;
-
-                let rotation: Quat = transform.rotation;
-                let direction: Vec3 = rotation * Vec3::X;
-
-                let x: f32 = force * direction.x;
-                let y: f32 = force * direction.y;
-                let z: f32 = force * direction.z;
-                let result: Vec3 = Vec3::new(x, y, z);
-
-                velocity += result;
-
-                linear.0 += velocity;
-            }
-        }
-    }
-}
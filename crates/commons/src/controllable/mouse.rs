@@ -1,23 +1,20 @@
-use super::Controllable;
-use crate::configs::GameConfigs;
-use avian3d::prelude::*;
+use super::{Controllable, ThrustInput};
+use crate::{
+    configs::{GameConfigs, player::MouseFeelConfig},
+    input::{Action, ActionState},
+};
 use bevy::{input::mouse::AccumulatedMouseMotion, prelude::*};
+use std::collections::VecDeque;
 
 /// Flight camera rotation
 /// Mouse control
 fn flight_camera(
     game_configs: &GameConfigs,
-    accumulated_mouse_motion: &AccumulatedMouseMotion,
+    mouse: Vec2,
     transform: &mut Transform,
-    angular: &mut AngularVelocity,
+    angular: &mut Vec3,
 ) {
-    if accumulated_mouse_motion.delta != Vec2::ZERO {
-        let delta = accumulated_mouse_motion.delta;
-
-        let x = -delta.x / 100.;
-        let y = -delta.y / 100.;
-        let mouse: Vec2 = Vec2::new(x, y);
-
+    if mouse != Vec2::ZERO {
         let rotation: Quat = transform.rotation;
 
         let mut velocity: Vec3 = Vec3::ZERO;
@@ -56,7 +53,7 @@ fn flight_camera(
             velocity += result * game_configs.player.robo.thruster.force.pitch;
         }
 
-        angular.0 += velocity;
+        *angular += velocity;
     }
 }
 
@@ -64,17 +61,11 @@ fn flight_camera(
 /// Mouse control
 fn normal_camera(
     game_configs: &GameConfigs,
-    accumulated_mouse_motion: &AccumulatedMouseMotion,
+    mouse: Vec2,
     transform: &mut Transform,
-    angular: &mut AngularVelocity,
+    angular: &mut Vec3,
 ) {
-    if accumulated_mouse_motion.delta != Vec2::ZERO {
-        let delta = accumulated_mouse_motion.delta;
-
-        let x = -delta.x / 100.;
-        let y = -delta.y / 100.;
-        let mouse: Vec2 = Vec2::new(x, y);
-
+    if mouse != Vec2::ZERO {
         let rotation: Quat = transform.rotation;
 
         let mut velocity: Vec3 = Vec3::ZERO;
@@ -113,31 +104,192 @@ fn normal_camera(
             velocity += result * game_configs.player.robo.thruster.force.pitch;
         }
 
-        angular.0 += velocity;
+        *angular += velocity;
+    }
+}
+
+/// Turns the frame's mouse motion into rotation, by the deadzone, smoothing, acceleration & sensitivity in order.
+/// `history` keeps the recent motions for smoothing.
+fn shape(delta: Vec2, config: &MouseFeelConfig, history: &mut VecDeque<Vec2>) -> Vec2 {
+    // Deadzone
+    let length: f32 = delta.length();
+    let delta: Vec2 = if length <= config.deadzone {
+        Vec2::ZERO
+    } else {
+        delta * (length - config.deadzone) / length
+    };
+
+    // Smoothing, averaged over the whole window so the total motion is kept
+    let frames: usize = config.smoothing.max(1);
+    history.push_back(delta);
+    while history.len() > frames {
+        history.pop_front();
     }
+    let delta: Vec2 = history.iter().sum::<Vec2>() / frames as f32;
+
+    // Acceleration
+    let delta: Vec2 = delta * (1. + config.acceleration * delta.length());
+
+    // Sensitivity. Moving the mouse right & down turns right & down.
+    -delta * Vec2::new(config.sensitivity_x, config.sensitivity_y)
 }
 
 pub fn update_system(
-    mut query: Query<(&mut Transform, &mut AngularVelocity), With<Controllable>>,
+    mut query: Query<(&mut Transform, &mut ThrustInput), With<Controllable>>,
     game_configs: Res<GameConfigs>,
     accumulated_mouse_motion: Res<AccumulatedMouseMotion>,
-    mouse_button: Res<ButtonInput<MouseButton>>,
+    actions: Res<ActionState>,
+    mut history: Local<VecDeque<Vec2>>,
 ) {
-    for (mut transform, mut angular) in query.iter_mut() {
-        if !mouse_button.pressed(MouseButton::Right) {
-            normal_camera(
-                &game_configs,
-                &accumulated_mouse_motion,
-                &mut transform,
-                &mut angular,
-            );
+    // Motion shaped by the other mode's deadzone & window isn't smoothed into this mode's
+    if actions.just_pressed(Action::FlightCamera) || actions.just_released(Action::FlightCamera) {
+        history.clear();
+    }
+
+    let flight: bool = actions.pressed(Action::FlightCamera);
+    let config: &MouseFeelConfig = if flight {
+        &game_configs.player.mouse.flight
+    } else {
+        &game_configs.player.mouse.normal
+    };
+    let mouse: Vec2 = shape(accumulated_mouse_motion.delta, config, &mut history);
+
+    for (mut transform, mut input) in query.iter_mut() {
+        if !flight {
+            normal_camera(&game_configs, mouse, &mut transform, &mut input.angular);
         } else {
-            flight_camera(
-                &game_configs,
-                &accumulated_mouse_motion,
-                &mut transform,
-                &mut angular,
+            flight_camera(&game_configs, mouse, &mut transform, &mut input.angular);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod shape {
+        use crate::{configs::player::MouseFeelConfig, controllable::mouse::shape};
+        use bevy::prelude::*;
+        use std::collections::VecDeque;
+
+        /// The default feel is the same as the former fixed 1/100 scale
+        #[test]
+        fn default() {
+            let mut history: VecDeque<Vec2> = VecDeque::new();
+            let mouse: Vec2 = shape(
+                Vec2::new(10., -20.),
+                &MouseFeelConfig::default(),
+                &mut history,
+            );
+
+            assert!(mouse.abs_diff_eq(Vec2::new(-0.1, 0.2), 1e-6));
+        }
+
+        /// Each axis has its own sensitivity
+        #[test]
+        fn sensitivity() {
+            let config: MouseFeelConfig = MouseFeelConfig {
+                sensitivity_x: 0.02,
+                sensitivity_y: 0.005,
+                ..Default::default()
+            };
+            let mouse: Vec2 = shape(Vec2::new(10., 10.), &config, &mut VecDeque::new());
+
+            assert!(mouse.abs_diff_eq(Vec2::new(-0.2, -0.05), 1e-6));
+        }
+
+        /// Small motion is ignored, and the deadzone is subtracted from larger one
+        #[test]
+        fn deadzone() {
+            let config: MouseFeelConfig = MouseFeelConfig {
+                deadzone: 2.,
+                sensitivity_x: 1.,
+                sensitivity_y: 1.,
+                ..Default::default()
+            };
+
+            assert_eq!(shape(Vec2::X, &config, &mut VecDeque::new()), Vec2::ZERO);
+            assert!(
+                shape(Vec2::X * 5., &config, &mut VecDeque::new())
+                    .abs_diff_eq(Vec2::NEG_X * 3., 1e-6)
             );
         }
+
+        /// Motion is spread over the frames, keeping its total
+        #[test]
+        fn smoothing() {
+            let config: MouseFeelConfig = MouseFeelConfig {
+                smoothing: 4,
+                sensitivity_x: 1.,
+                sensitivity_y: 1.,
+                ..Default::default()
+            };
+            let mut history: VecDeque<Vec2> = VecDeque::new();
+
+            let first: Vec2 = shape(Vec2::X * 8., &config, &mut history);
+            let total: Vec2 = (0..10).fold(first, |total, _| {
+                total + shape(Vec2::ZERO, &config, &mut history)
+            });
+
+            assert!(first.abs_diff_eq(Vec2::NEG_X * 2., 1e-6));
+            assert!(total.abs_diff_eq(Vec2::NEG_X * 8., 1e-6));
+        }
+
+        /// Faster motion turns further than proportionally
+        #[test]
+        fn acceleration() {
+            let config: MouseFeelConfig = MouseFeelConfig {
+                acceleration: 0.1,
+                sensitivity_x: 1.,
+                sensitivity_y: 1.,
+                ..Default::default()
+            };
+
+            assert!(
+                shape(Vec2::X * 10., &config, &mut VecDeque::new())
+                    .abs_diff_eq(Vec2::NEG_X * 20., 1e-6)
+            );
+        }
+    }
+
+    mod update_system {
+        use crate::{
+            Controllable, ThrustInput, configs::GameConfigs, controllable::mouse::update_system,
+            input::ActionPlugin,
+        };
+        use bevy::{
+            input::{InputPlugin, mouse::MouseMotion},
+            prelude::*,
+        };
+
+        /// Smoothing doesn't carry the motion of a camera mode into the other one
+        #[test]
+        fn switch_camera() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.mouse.normal.smoothing = 4;
+            configs.player.mouse.flight.smoothing = 4;
+
+            let mut app: App = App::new();
+            app.add_plugins((MinimalPlugins, InputPlugin, ActionPlugin))
+                .insert_resource(configs)
+                .add_systems(Update, update_system);
+            let robo: Entity = app
+                .world_mut()
+                .spawn((Transform::default(), Controllable))
+                .id();
+            let angular = |app: &App| app.world().get::<ThrustInput>(robo).unwrap().angular;
+
+            app.world_mut().write_message(MouseMotion {
+                delta: Vec2::X * 8.,
+            });
+            app.update();
+            let normal: Vec3 = angular(&app);
+            assert_ne!(normal, Vec3::ZERO);
+
+            // The flight camera starts without the motion left in the normal camera's window
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Right);
+            app.update();
+            assert_eq!(angular(&app), normal);
+        }
     }
 }
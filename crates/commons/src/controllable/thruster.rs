@@ -0,0 +1,416 @@
+use super::{Controllable, Fuel};
+use crate::configs::GameConfigs;
+use avian3d::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// ForceConfig values are velocity changes per frame at this rate, as they used to be applied once per frame
+pub const REFERENCE_RATE: f32 = 60.0;
+
+/// The ratio of velocities which hovering keeps per reference frame
+const HOVER_DAMPING: f32 = 0.7;
+
+/// Thrust requested by input in Update, which is applied in FixedUpdate
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+pub struct ThrustInput {
+    /// Linear thrust in world space, in velocity change per reference frame
+    pub linear: Vec3,
+
+    /// Angular thrust in world space, in angular velocity change per reference frame
+    pub torque: Vec3,
+
+    /// Angular velocity change accumulated since the last fixed tick
+    pub angular: Vec3,
+
+    /// Whether the robo is braking
+    pub hover: bool,
+
+    /// Whether the rotation thrusters stop the spin
+    pub kill_rotation: bool,
+}
+
+/// How the thrusters help the pilot
+#[derive(Component, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FlightAssist {
+    /// Thrust is applied as it is, and the robo keeps drifting
+    #[default]
+    Newtonian,
+
+    /// Thrusters counter-thrust to reach the velocity commanded by the keys, and to stop when no key is pressed
+    Assisted,
+}
+
+impl FlightAssist {
+    pub fn toggle(&mut self) {
+        *self = match self {
+            Self::Newtonian => Self::Assisted,
+            Self::Assisted => Self::Newtonian,
+        };
+    }
+}
+
+/// Linear thrust toward the target velocity, in velocity change per reference frame, within the limit
+pub fn assist(velocity: Vec3, target: Vec3, limit: f32, delta: f32) -> Vec3 {
+    if delta <= 0. {
+        return Vec3::ZERO;
+    }
+
+    ((target - velocity) / (REFERENCE_RATE * delta)).clamp_length_max(limit)
+}
+
+/// Angular thrust which stops the spin, in angular velocity change per reference frame.
+/// `limits` are the local pitch, yaw & roll thrusters' limits.
+pub fn kill_rotation(rotation: Quat, angular: Vec3, limits: Vec3, delta: f32) -> Vec3 {
+    if delta <= 0. {
+        return Vec3::ZERO;
+    }
+
+    let local: Vec3 = rotation.inverse() * -angular / (REFERENCE_RATE * delta);
+
+    rotation * local.clamp(-limits, limits)
+}
+
+/// Thrust which burns fuel, as the sum of every local axis' thruster.
+/// Firing two axes at once burns as much as firing them one by one.
+pub fn fuel_used(rotation: Quat, linear: Vec3, torque: Vec3) -> f32 {
+    let inverse: Quat = rotation.inverse();
+
+    (inverse * linear).abs().element_sum() + (inverse * torque).abs().element_sum()
+}
+
+/// A velocity change which doesn't push the speed over the cap.
+/// A change which slows down is kept, so the robo can always brake even if something else pushed it over the cap.
+pub fn cap(velocity: Vec3, change: Vec3, max: f32) -> Vec3 {
+    let limit: f32 = max.max(velocity.length());
+    let next: Vec3 = velocity + change;
+
+    if next.length() <= limit {
+        change
+    } else {
+        next.clamp_length_max(limit) - velocity
+    }
+}
+
+/// Applies the requested thrust as accelerations scaled by the fixed timestep
+pub fn apply_system(
+    mut query: Query<
+        (&mut ThrustInput, &FlightAssist, Forces, Option<&mut Fuel>),
+        With<Controllable>,
+    >,
+    game_configs: Res<GameConfigs>,
+    time: Res<Time>,
+) {
+    let delta: f32 = time.delta_secs();
+    let thruster = &game_configs.player.robo.thruster;
+    if delta <= 0. {
+        return;
+    }
+
+    for (mut input, flight_assist, mut forces, fuel) in query.iter_mut() {
+        // Hovering
+        if input.hover {
+            let damping: f32 = HOVER_DAMPING.powf(REFERENCE_RATE * delta);
+            *forces.linear_velocity_mut() *= damping;
+            *forces.angular_velocity_mut() *= damping;
+        }
+
+        let mut linear: Vec3 = input.linear;
+        let mut torque: Vec3 = input.torque;
+
+        // Counter-thrust toward the commanded velocity, at least with the accelerate thrusters
+        if *flight_assist == FlightAssist::Assisted {
+            let target: Vec3 = input.linear.normalize_or_zero() * thruster.assist_speed;
+            let limit: f32 = input.linear.length().max(thruster.force.accelerate);
+            linear = assist(forces.linear_velocity(), target, limit, delta);
+        }
+
+        // Each local axis' thrusters have their own limit
+        let rotation: Quat = forces.rotation().0;
+        let limits: Vec3 = thruster.axis_limits.to_vec3();
+        linear = rotation * (rotation.inverse() * linear).clamp(-limits, limits);
+
+        if input.kill_rotation {
+            let force = &thruster.force;
+            let limits: Vec3 = Vec3::new(force.pitch, force.yaw, force.roll) * force.kill_rotation;
+            torque += kill_rotation(rotation, forces.angular_velocity(), limits, delta);
+        }
+
+        // Mouse motion is a displacement rather than a rate, so it is applied as it is
+        let angular: Vec3 = cap(
+            forces.angular_velocity(),
+            input.angular,
+            thruster.max_angular_rate,
+        );
+        *forces.angular_velocity_mut() += angular;
+        input.angular = Vec3::ZERO;
+
+        // Thrust which the caps let through, in velocity change per reference frame
+        let scale: f32 = REFERENCE_RATE * delta;
+        let linear: Vec3 =
+            cap(forces.linear_velocity(), linear * scale, thruster.max_speed) / scale;
+        let torque: Vec3 = cap(
+            forces.angular_velocity(),
+            torque * scale,
+            thruster.max_angular_rate,
+        ) / scale;
+
+        // Thruster fuel, burnt by the thrust applied. Robos without Fuel have unlimited thrust.
+        // It regenerates while the pilot commands no thrust, even if the flight assist trims a drift.
+        let commanded: bool =
+            input.linear != Vec3::ZERO || input.torque != Vec3::ZERO || input.kill_rotation;
+        let used: f32 = fuel_used(rotation, linear, torque);
+        let ratio: f32 = match fuel {
+            Some(mut fuel) => {
+                if !commanded {
+                    fuel.regenerate(delta);
+                }
+                fuel.burn(used * delta)
+            }
+            None => 1.,
+        };
+
+        forces.apply_linear_acceleration(linear * REFERENCE_RATE * ratio);
+        forces.apply_angular_acceleration(torque * REFERENCE_RATE * ratio);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod apply_system {
+        use crate::{
+            Controllable, FlightAssist, Fuel,
+            configs::GameConfigs,
+            controllable::thruster::{REFERENCE_RATE, ThrustInput, apply_system},
+            testing::{physics_app, update},
+        };
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use std::time::Duration;
+
+        /// Runs a second at the given frame rate, and returns the robo's velocity
+        fn run(frame_rate: u32, input: ThrustInput, velocity: Vec3) -> Vec3 {
+            let mut app: App = physics_app(Duration::from_secs_f64(1. / frame_rate as f64));
+            app.insert_resource(GameConfigs::default());
+            app.add_systems(FixedUpdate, apply_system);
+
+            let robo: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Dynamic,
+                    Collider::sphere(1.0),
+                    Mass(5.0),
+                    LinearVelocity(velocity),
+                    Controllable,
+                    input,
+                ))
+                .id();
+
+            update(&mut app, frame_rate as usize + 1);
+
+            app.world().get::<LinearVelocity>(robo).unwrap().0
+        }
+
+        /// Acceleration doesn't depend on the frame rate
+        #[test]
+        fn frame_rate_independent() {
+            let input: ThrustInput = ThrustInput {
+                linear: Vec3::NEG_Z * 0.7,
+                ..Default::default()
+            };
+
+            let slow: Vec3 = run(60, input.clone(), Vec3::ZERO);
+            let fast: Vec3 = run(144, input, Vec3::ZERO);
+
+            // About 0.7 per reference frame for a second
+            assert!((slow.z + 0.7 * REFERENCE_RATE).abs() < 2.0, "{slow}");
+            assert!(slow.abs_diff_eq(fast, 1.0), "{slow} != {fast}");
+        }
+
+        /// Dashing can't speed the robo up over the max speed
+        #[test]
+        fn max_speed() {
+            let input: ThrustInput = ThrustInput {
+                linear: Vec3::NEG_Z * 3.7,
+                ..Default::default()
+            };
+
+            let velocity: Vec3 = run(60, input, Vec3::ZERO);
+            let max_speed: f32 = GameConfigs::default().player.robo.thruster.max_speed;
+
+            assert!((velocity.length() - max_speed).abs() < 1e-3, "{velocity}");
+        }
+
+        /// An idle pilot's fuel regenerates in Assisted mode, while the assist trims a slight drift
+        #[test]
+        fn assisted_regeneration() {
+            let mut app: App = physics_app(Duration::from_secs_f64(1. / 60.));
+            app.insert_resource(GameConfigs::default());
+            app.add_systems(FixedUpdate, apply_system);
+
+            let mut fuel: Fuel = Fuel::new(100., 20., 1.);
+            fuel.rest = 50.;
+            let robo: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Dynamic,
+                    Collider::sphere(1.0),
+                    Mass(5.0),
+                    LinearVelocity(Vec3::new(1e-3, -2e-4, 5e-4)),
+                    Controllable,
+                    FlightAssist::Assisted,
+                    fuel,
+                ))
+                .id();
+
+            update(&mut app, 61);
+
+            // About 20 regenerated in a second
+            let rest: f32 = app.world().get::<Fuel>(robo).unwrap().rest;
+            assert!(rest > 65., "{rest}");
+        }
+
+        /// Holding forward at the max speed burns no fuel, as the cap lets no thrust through
+        #[test]
+        fn capped_fuel() {
+            let max_speed: f32 = GameConfigs::default().player.robo.thruster.max_speed;
+            let mut app: App = physics_app(Duration::from_secs_f64(1. / 60.));
+            app.insert_resource(GameConfigs::default());
+            app.add_systems(FixedUpdate, apply_system);
+
+            let robo: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Dynamic,
+                    Collider::sphere(1.0),
+                    Mass(5.0),
+                    LinearVelocity(Vec3::NEG_Z * max_speed),
+                    Controllable,
+                    ThrustInput {
+                        linear: Vec3::NEG_Z * 3.7,
+                        ..Default::default()
+                    },
+                    Fuel::new(100., 0., 1.),
+                ))
+                .id();
+
+            update(&mut app, 61);
+
+            let rest: f32 = app.world().get::<Fuel>(robo).unwrap().rest;
+            assert!(rest > 99.9, "{rest}");
+        }
+
+        /// Hovering brakes equally on every frame rate
+        #[test]
+        fn hover() {
+            let input: ThrustInput = ThrustInput {
+                hover: true,
+                ..Default::default()
+            };
+
+            let slow: Vec3 = run(60, input.clone(), Vec3::X * 100.0);
+            let fast: Vec3 = run(144, input, Vec3::X * 100.0);
+
+            assert!(slow.x < 1.0, "{slow}");
+            assert!(slow.abs_diff_eq(fast, 0.1), "{slow} != {fast}");
+        }
+    }
+
+    mod assist {
+        use crate::controllable::thruster::{REFERENCE_RATE, assist};
+        use bevy::prelude::*;
+
+        /// Without input, the thrusters brake within their limit
+        #[test]
+        fn brake() {
+            let thrust: Vec3 = assist(Vec3::X * 100., Vec3::ZERO, 0.7, 1. / 64.);
+            assert!(thrust.abs_diff_eq(Vec3::NEG_X * 0.7, 1e-6));
+        }
+
+        /// Close to the target, the thrust just reaches it in a tick
+        #[test]
+        fn reach() {
+            let delta: f32 = 1. / 64.;
+            let thrust: Vec3 = assist(Vec3::Z * 9.9, Vec3::Z * 10., 0.7, delta);
+            let change: Vec3 = thrust * REFERENCE_RATE * delta;
+
+            assert!(change.abs_diff_eq(Vec3::Z * 0.1, 1e-4));
+        }
+    }
+
+    mod kill_rotation {
+        use crate::controllable::thruster::kill_rotation;
+        use bevy::prelude::*;
+
+        /// Each local axis is countered within its own thruster's limit
+        #[test]
+        fn limits() {
+            let rotation: Quat = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+            let angular: Vec3 = rotation * Vec3::new(10., 0., 0.001);
+            let limits: Vec3 = Vec3::new(0.1, 0.1, 0.2);
+
+            let torque: Vec3 = kill_rotation(rotation, angular, limits, 1. / 64.);
+            let local: Vec3 = rotation.inverse() * torque;
+
+            // Pitch is limited, and roll is stopped exactly
+            assert!((local.x + 0.1).abs() < 1e-5, "{local}");
+            assert!((local.z + 0.001 * 64. / 60.).abs() < 1e-5, "{local}");
+        }
+    }
+
+    mod fuel_used {
+        use crate::controllable::thruster::fuel_used;
+        use bevy::prelude::*;
+
+        /// Diagonal thrust burns as much as the same thrusters fired on separate axes
+        #[test]
+        fn diagonal() {
+            let rotation: Quat = Quat::from_rotation_y(0.3);
+            let forward: Vec3 = rotation * Vec3::NEG_Z;
+            let right: Vec3 = rotation * Vec3::X;
+
+            let separate: f32 =
+                fuel_used(rotation, forward, Vec3::ZERO) + fuel_used(rotation, right, Vec3::ZERO);
+            let diagonal: f32 = fuel_used(rotation, forward + right, Vec3::ZERO);
+
+            assert!((separate - 2.).abs() < 1e-5, "{separate}");
+            assert!((diagonal - separate).abs() < 1e-5, "{diagonal}");
+        }
+
+        /// Torque burns fuel too
+        #[test]
+        fn torque() {
+            assert_eq!(
+                fuel_used(Quat::IDENTITY, Vec3::X, Vec3::new(0., 2., 1.)),
+                4.
+            );
+        }
+    }
+
+    mod cap {
+        use crate::controllable::thruster::cap;
+        use bevy::prelude::*;
+
+        /// A change under the cap is kept as it is
+        #[test]
+        fn under() {
+            assert_eq!(cap(Vec3::X, Vec3::X, 10.), Vec3::X);
+        }
+
+        /// A change over the cap is cut at the cap
+        #[test]
+        fn over() {
+            let change: Vec3 = cap(Vec3::X * 9., Vec3::X * 3., 10.);
+            assert!(change.abs_diff_eq(Vec3::X, 1e-6));
+        }
+
+        /// Braking is possible even over the cap, but speeding up is not
+        #[test]
+        fn already_over() {
+            assert_eq!(cap(Vec3::X * 20., Vec3::NEG_X, 10.), Vec3::NEG_X);
+            assert_eq!(cap(Vec3::X * 20., Vec3::X, 10.), Vec3::ZERO);
+        }
+    }
+}
@@ -0,0 +1,486 @@
+//! # Input actions
+//!
+//! Systems read what the player means to do as actions, rather than which key or button is pressed.
+//! Each action is bound to keys, mouse buttons, gamepad buttons or gamepad axes in `player.actions` of GameConfigs.
+
+use crate::configs::GameConfigs;
+use bevy::{
+    input::{InputSystems, gamepad::Gamepad},
+    platform::collections::HashMap,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+/// Actions are pressed while their value is at least this
+pub const PRESS_THRESHOLD: f32 = 0.5;
+
+/// What the player can do
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    // Movements
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+    RollLeft,
+    RollRight,
+    Dash,
+
+    // Rotations, mostly by a gamepad stick
+    PitchUp,
+    PitchDown,
+    YawLeft,
+    YawRight,
+
+    // Hovering & flight assist
+    Hover,
+    ToggleFlightAssist,
+    KillRotation,
+
+    // Gun
+    Fire,
+    #[serde(rename = "toggle_firemode")]
+    ToggleFireMode,
+    Reload,
+
+    // Camera
+    FlightCamera,
+
+    Respawn,
+
+    /// Leaves the shooting range, or exits on the title screen
+    Quit,
+
+    /// Starts the shooting range on the title screen
+    Start,
+}
+
+impl Action {
+    pub const ALL: [Action; 23] = [
+        Action::Forward,
+        Action::Back,
+        Action::Left,
+        Action::Right,
+        Action::Up,
+        Action::Down,
+        Action::RollLeft,
+        Action::RollRight,
+        Action::Dash,
+        Action::PitchUp,
+        Action::PitchDown,
+        Action::YawLeft,
+        Action::YawRight,
+        Action::Hover,
+        Action::ToggleFlightAssist,
+        Action::KillRotation,
+        Action::Fire,
+        Action::ToggleFireMode,
+        Action::Reload,
+        Action::FlightCamera,
+        Action::Respawn,
+        Action::Quit,
+        Action::Start,
+    ];
+
+    /// The name in config files
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Forward => "forward",
+            Action::Back => "back",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::RollLeft => "roll_left",
+            Action::RollRight => "roll_right",
+            Action::Dash => "dash",
+            Action::PitchUp => "pitch_up",
+            Action::PitchDown => "pitch_down",
+            Action::YawLeft => "yaw_left",
+            Action::YawRight => "yaw_right",
+            Action::Hover => "hover",
+            Action::ToggleFlightAssist => "toggle_flight_assist",
+            Action::KillRotation => "kill_rotation",
+            Action::Fire => "fire",
+            Action::ToggleFireMode => "toggle_firemode",
+            Action::Reload => "reload",
+            Action::FlightCamera => "flight_camera",
+            Action::Respawn => "respawn",
+            Action::Quit => "quit",
+            Action::Start => "start",
+        }
+    }
+
+    /// Whether the action is read on the title screen
+    pub fn on_title(self) -> bool {
+        matches!(self, Action::Start | Action::Quit)
+    }
+
+    /// Whether the action is read in the shooting range
+    pub fn in_game(self) -> bool {
+        self != Action::Start
+    }
+
+    /// Default mouse & gamepad bindings, and keys which KeyboardConfig has no field for
+    pub fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{AxisNegative, AxisPositive, Gamepad, Key, Mouse};
+
+        match self {
+            Action::Forward => vec![AxisPositive(GamepadAxis::LeftStickY)],
+            Action::Back => vec![AxisNegative(GamepadAxis::LeftStickY)],
+            Action::Left => vec![AxisNegative(GamepadAxis::LeftStickX)],
+            Action::Right => vec![AxisPositive(GamepadAxis::LeftStickX)],
+            Action::Up => vec![Gamepad(GamepadButton::DPadUp)],
+            Action::Down => vec![Gamepad(GamepadButton::DPadDown)],
+            Action::RollLeft => vec![Gamepad(GamepadButton::LeftTrigger)],
+            Action::RollRight => vec![Gamepad(GamepadButton::RightTrigger)],
+            Action::Dash => vec![Gamepad(GamepadButton::LeftTrigger2)],
+            Action::PitchUp => vec![AxisPositive(GamepadAxis::RightStickY)],
+            Action::PitchDown => vec![AxisNegative(GamepadAxis::RightStickY)],
+            Action::YawLeft => vec![AxisNegative(GamepadAxis::RightStickX)],
+            Action::YawRight => vec![AxisPositive(GamepadAxis::RightStickX)],
+            Action::Hover => vec![Gamepad(GamepadButton::East)],
+            Action::ToggleFlightAssist => vec![Gamepad(GamepadButton::DPadLeft)],
+            Action::KillRotation => vec![Gamepad(GamepadButton::RightThumb)],
+            Action::Fire => vec![
+                Mouse(MouseButton::Left),
+                Gamepad(GamepadButton::RightTrigger2),
+            ],
+            Action::ToggleFireMode => vec![Gamepad(GamepadButton::North)],
+            Action::Reload => vec![Gamepad(GamepadButton::West)],
+            Action::FlightCamera => vec![Mouse(MouseButton::Right)],
+            Action::Respawn => vec![Gamepad(GamepadButton::Start)],
+            Action::Quit => vec![Gamepad(GamepadButton::Select)],
+            Action::Start => vec![Key(KeyCode::Space), Gamepad(GamepadButton::Start)],
+        }
+    }
+}
+
+/// An input which an action is bound to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+
+    /// The positive half of a gamepad axis, such as pushing a stick up or right
+    AxisPositive(GamepadAxis),
+
+    /// The negative half of a gamepad axis, such as pushing a stick down or left
+    AxisNegative(GamepadAxis),
+}
+
+impl Binding {
+    /// The value of the input from 0.0 to 1.0. Gamepad inputs take the most pushed of all gamepads.
+    /// Axis values up to `deadzone` are ignored.
+    pub fn value(
+        &self,
+        keyboard: &ButtonInput<KeyCode>,
+        mouse: &ButtonInput<MouseButton>,
+        gamepads: &Query<&Gamepad>,
+        deadzone: f32,
+    ) -> f32 {
+        let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+
+        match *self {
+            Binding::Key(key) => pressed(keyboard.pressed(key)),
+            Binding::Mouse(button) => pressed(mouse.pressed(button)),
+            Binding::Gamepad(button) => gamepads
+                .iter()
+                .map(|gamepad| {
+                    let digital: f32 = pressed(gamepad.pressed(button));
+                    gamepad.get(button).unwrap_or(0.0).max(digital)
+                })
+                .fold(0.0, f32::max),
+            Binding::AxisPositive(axis) => axis_value(gamepads, axis, 1.0, deadzone),
+            Binding::AxisNegative(axis) => axis_value(gamepads, axis, -1.0, deadzone),
+        }
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::Gamepad(button) => write!(f, "Gamepad {button:?}"),
+            Binding::AxisPositive(axis) => write!(f, "Gamepad {axis:?}+"),
+            Binding::AxisNegative(axis) => write!(f, "Gamepad {axis:?}-"),
+        }
+    }
+}
+
+/// A half of the axis outside the deadzone, rescaled from 0.0 to 1.0
+fn axis_value(gamepads: &Query<&Gamepad>, axis: GamepadAxis, sign: f32, deadzone: f32) -> f32 {
+    let deadzone: f32 = deadzone.clamp(0.0, 0.99);
+
+    gamepads
+        .iter()
+        .map(|gamepad| {
+            let value: f32 = gamepad.get(axis).unwrap_or(0.0) * sign;
+            ((value - deadzone) / (1.0 - deadzone)).clamp(0.0, 1.0)
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Values of the actions in the current frame, and in the previous frame to detect presses & releases
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    previous: HashMap<Action, f32>,
+}
+
+impl ActionState {
+    /// The value of the action from 0.0 to 1.0. Analog inputs give values in between.
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.value(action) >= PRESS_THRESHOLD
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed(action) && !self.was_pressed(action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        !self.pressed(action) && self.was_pressed(action)
+    }
+
+    fn was_pressed(&self, action: Action) -> bool {
+        self.previous.get(&action).copied().unwrap_or(0.0) >= PRESS_THRESHOLD
+    }
+
+    /// Starts a new frame, keeping the current values as the previous ones
+    pub fn advance(&mut self) {
+        self.previous = std::mem::take(&mut self.values);
+    }
+
+    pub fn set(&mut self, action: Action, value: f32) {
+        self.values.insert(action, value);
+    }
+}
+
+/// The system set which updates ActionState. Systems which feed inputs, such as a replay, run before it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSystems;
+
+/// A plugin to update ActionState from the bound inputs every frame
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        // Apps without InputPlugin, such as tests, still have the device inputs
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app.init_resource::<ButtonInput<MouseButton>>();
+        app.init_resource::<ActionState>();
+        app.add_systems(
+            PreUpdate,
+            update_system.in_set(ActionSystems).after(InputSystems),
+        );
+    }
+}
+
+/// Resolves every action's value from its bindings. The most pushed binding wins.
+pub fn update_system(
+    mut state: ResMut<ActionState>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    game_configs: Res<GameConfigs>,
+) {
+    let deadzone: f32 = game_configs.player.gamepad.deadzone;
+    state.advance();
+
+    for action in Action::ALL {
+        let value: f32 = game_configs
+            .player
+            .bindings(action)
+            .iter()
+            .map(|binding| binding.value(&keyboard, &mouse, &gamepads, deadzone))
+            .fold(0.0, f32::max);
+
+        if value > 0.0 {
+            state.set(action, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod action_state {
+        use crate::input::{Action, ActionState};
+
+        /// Presses & releases are detected against the previous frame
+        #[test]
+        fn just_pressed() {
+            let mut state: ActionState = ActionState::default();
+
+            state.advance();
+            state.set(Action::Fire, 1.0);
+            assert!(state.just_pressed(Action::Fire));
+
+            state.advance();
+            state.set(Action::Fire, 1.0);
+            assert!(state.pressed(Action::Fire));
+            assert!(!state.just_pressed(Action::Fire));
+
+            state.advance();
+            assert!(state.just_released(Action::Fire));
+        }
+
+        /// An analog value under the threshold isn't a press
+        #[test]
+        fn analog() {
+            let mut state: ActionState = ActionState::default();
+            state.set(Action::Forward, 0.3);
+
+            assert_eq!(state.value(Action::Forward), 0.3);
+            assert!(!state.pressed(Action::Forward));
+        }
+    }
+
+    mod action {
+        use crate::input::Action;
+
+        /// Names are the same as the config files'
+        #[test]
+        fn name() {
+            for action in Action::ALL {
+                assert_eq!(ron::to_string(&action).unwrap(), action.name());
+            }
+        }
+
+        /// ALL lists every action in order.
+        /// A new action doesn't compile here until it is chained in, and then fails until it is in ALL.
+        #[test]
+        fn all() {
+            let next = |action: Action| match action {
+                Action::Forward => Some(Action::Back),
+                Action::Back => Some(Action::Left),
+                Action::Left => Some(Action::Right),
+                Action::Right => Some(Action::Up),
+                Action::Up => Some(Action::Down),
+                Action::Down => Some(Action::RollLeft),
+                Action::RollLeft => Some(Action::RollRight),
+                Action::RollRight => Some(Action::Dash),
+                Action::Dash => Some(Action::PitchUp),
+                Action::PitchUp => Some(Action::PitchDown),
+                Action::PitchDown => Some(Action::YawLeft),
+                Action::YawLeft => Some(Action::YawRight),
+                Action::YawRight => Some(Action::Hover),
+                Action::Hover => Some(Action::ToggleFlightAssist),
+                Action::ToggleFlightAssist => Some(Action::KillRotation),
+                Action::KillRotation => Some(Action::Fire),
+                Action::Fire => Some(Action::ToggleFireMode),
+                Action::ToggleFireMode => Some(Action::Reload),
+                Action::Reload => Some(Action::FlightCamera),
+                Action::FlightCamera => Some(Action::Respawn),
+                Action::Respawn => Some(Action::Quit),
+                Action::Quit => Some(Action::Start),
+                Action::Start => None,
+            };
+
+            let chain: Vec<Action> =
+                std::iter::successors(Some(Action::Forward), |action| next(*action)).collect();
+
+            assert_eq!(chain, Action::ALL);
+            assert!(chain.is_sorted(), "The chain must follow the declaration");
+        }
+    }
+
+    mod update_system {
+        use crate::{
+            configs::GameConfigs,
+            input::{Action, ActionPlugin, ActionState, Binding},
+        };
+        use bevy::{input::gamepad::Gamepad, prelude::*};
+
+        fn app(configs: GameConfigs) -> App {
+            let mut app: App = App::new();
+            app.add_plugins((MinimalPlugins, ActionPlugin));
+            app.insert_resource(configs);
+
+            app
+        }
+
+        /// Default bindings come from KeyboardConfig and the mouse
+        #[test]
+        fn default_bindings() {
+            let mut app: App = app(GameConfigs::default());
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<KeyCode>>()
+                .press(KeyCode::KeyW);
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+            app.update();
+
+            let state = app.world().resource::<ActionState>();
+            assert!(state.just_pressed(Action::Forward));
+            assert!(state.just_pressed(Action::Fire));
+            assert!(!state.pressed(Action::Back));
+        }
+
+        /// Configured bindings replace the defaults of the action
+        #[test]
+        fn configured_bindings() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs
+                .player
+                .actions
+                .insert(Action::Fire, vec![Binding::Key(KeyCode::KeyJ)]);
+            let mut app: App = app(configs);
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+            app.update();
+            assert!(!app.world().resource::<ActionState>().pressed(Action::Fire));
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<KeyCode>>()
+                .press(KeyCode::KeyJ);
+            app.update();
+            assert!(app.world().resource::<ActionState>().pressed(Action::Fire));
+        }
+
+        /// Gamepad axes are split into halves, and the deadzone is cut off
+        #[test]
+        fn gamepad_axis() {
+            let mut app: App = app(GameConfigs::default());
+
+            let mut gamepad: Gamepad = Gamepad::default();
+            gamepad.analog_mut().set(GamepadAxis::LeftStickY, -0.575);
+            gamepad.analog_mut().set(GamepadAxis::LeftStickX, 0.1);
+            app.world_mut().spawn(gamepad);
+            app.update();
+
+            let state = app.world().resource::<ActionState>();
+            assert!((state.value(Action::Back) - 0.5).abs() < 1e-5);
+            assert_eq!(state.value(Action::Forward), 0.0);
+            assert_eq!(state.value(Action::Right), 0.0);
+        }
+
+        /// The deadzone is configurable
+        #[test]
+        fn configured_deadzone() {
+            let mut configs: GameConfigs = GameConfigs::default();
+            configs.player.gamepad.deadzone = 0.5;
+            let mut app: App = app(configs);
+
+            let mut gamepad: Gamepad = Gamepad::default();
+            gamepad.analog_mut().set(GamepadAxis::RightStickX, 0.4);
+            app.world_mut().spawn(gamepad);
+            app.update();
+
+            let state = app.world().resource::<ActionState>();
+            assert_eq!(state.value(Action::YawRight), 0.0);
+        }
+    }
+}
@@ -1,20 +1,36 @@
 //! Spacerobo commons
 
-use bevy::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
+use configs::GameConfigs;
+use serde::{Deserialize, Serialize};
 
 pub mod configs;
 mod controllable;
+pub mod input;
+pub mod replay;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
-pub use controllable::{Controllable, ControllablePlugin};
+pub use controllable::{Controllable, ControllablePlugin, FlightAssist, Fuel, ThrustInput};
 
 #[derive(Debug, Message)]
 pub struct DeathMessage {
     pub entity: Entity,
+
+    /// The entity which caused this death
+    pub instigator: Option<Entity>,
 }
 
 impl DeathMessage {
     pub fn new(entity: Entity) -> Self {
-        Self { entity }
+        Self {
+            entity,
+            instigator: None,
+        }
+    }
+
+    pub fn killed_by(entity: Entity, instigator: Option<Entity>) -> Self {
+        Self { entity, instigator }
     }
 }
 
@@ -22,6 +38,40 @@ impl DeathMessage {
 pub struct Damage {
     pub target: Entity,
     pub amount: f32,
+    pub kind: DamageKind,
+
+    /// The entity which caused this damage, such as the owner of a bullet
+    pub instigator: Option<Entity>,
+}
+
+/// What caused a damage, which Resistances are looked up with
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageKind {
+    /// Projectiles' impacts
+    #[default]
+    Kinetic,
+
+    /// Beams
+    Energy,
+
+    /// Blasts
+    Explosive,
+
+    /// Bodies ramming each other
+    Collision,
+
+    /// Hits by a gun itself
+    Melee,
+}
+
+/// A marker component for an entity which is recycled by its pool instead of being despawned
+#[derive(Debug, Component)]
+pub struct Pooled;
+
+/// Asks the pool to take a Pooled entity back
+#[derive(Debug, Event)]
+pub struct Recycle {
+    pub entity: Entity,
 }
 
 #[derive(Debug, States, Default, Hash, Eq, PartialEq, Clone)]
@@ -51,6 +101,42 @@ impl KillCounter {
     }
 }
 
+/// Kill & death tallies for each entity.
+/// They are kept after the entity is despawned.
+#[derive(Debug, Resource, Default)]
+pub struct KillTallies {
+    inner: HashMap<Entity, Tally>,
+}
+
+/// Kill & death counts of an entity
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub kills: usize,
+    pub deaths: usize,
+}
+
+impl KillTallies {
+    /// Records a death, crediting the kill to the instigator.
+    /// A kill by oneself is not credited.
+    pub fn record(&mut self, victim: Entity, instigator: Option<Entity>) {
+        self.inner.entry(victim).or_default().deaths += 1;
+
+        if let Some(instigator) = instigator
+            && instigator != victim
+        {
+            self.inner.entry(instigator).or_default().kills += 1;
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Tally {
+        self.inner.get(&entity).copied().unwrap_or_default()
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.clear();
+    }
+}
+
 #[derive(Debug, Component)]
 pub struct Hp {
     pub rest: f32,
@@ -69,15 +155,6 @@ impl std::default::Default for Hp {
 }
 
 pub trait Bullet {
-    fn shoot(
-        commands: &mut Commands,
-        meshes: &mut ResMut<Assets<Mesh>>,
-        materials: &mut ResMut<Assets<StandardMaterial>>,
-        origin: Vec3,
-        force: Vec3,
-        owner: Entity,
-    );
-
     fn gunfire_sound(commands: &mut Commands, asset_server: &Res<AssetServer>, place: Vec3);
     fn owner(&self) -> Entity;
     fn bounce_count(&self) -> usize;
@@ -90,6 +167,7 @@ pub trait Player {
         materials: &mut ResMut<Assets<StandardMaterial>>,
         kill_counter: &mut ResMut<KillCounter>,
         asset_server: Res<AssetServer>,
+        game_configs: &GameConfigs,
     );
 }
 
@@ -101,7 +179,7 @@ pub trait Target {
         materials: &mut ResMut<Assets<StandardMaterial>>,
         base_color: Color,
         position: Vec3,
-    );
+    ) -> Entity;
 }
 
 impl Hp {
@@ -139,6 +217,141 @@ impl Hp {
     }
 }
 
+/// A damage absorbing layer on top of Hp, which regenerates after a delay since the last hit
+#[derive(Debug, Component, Clone, PartialEq)]
+pub struct Shield {
+    pub rest: f32,
+    pub maximum: f32,
+
+    /// Regenerated amount per second
+    pub regeneration: f32,
+
+    /// Seconds from the last hit until the regeneration starts
+    pub delay: f32,
+
+    /// Seconds since the last hit
+    pub since_hit: f32,
+}
+
+impl Shield {
+    pub fn new(maximum: f32, regeneration: f32, delay: f32) -> Self {
+        Self {
+            rest: maximum,
+            maximum,
+            regeneration,
+            delay,
+            since_hit: delay,
+        }
+    }
+
+    /// Absorbs the damage as much as possible, and returns the rest of it which goes through the shield
+    pub fn absorb(&mut self, amount: f32) -> f32 {
+        let absorbed: f32 = amount.min(self.rest);
+        self.rest -= absorbed;
+        self.since_hit = 0.;
+
+        amount - absorbed
+    }
+
+    /// Regenerates the shield once the delay since the last hit is over
+    pub fn regenerate(&mut self, delta: f32) {
+        self.since_hit += delta;
+
+        if self.since_hit >= self.delay {
+            self.rest = (self.rest + self.regeneration * delta).min(self.maximum);
+        }
+    }
+}
+
+/// Takes Collision damage when the body rams into another CollisionDamage body, in proportion to the contact impulse
+#[derive(Debug, Component, Clone, Copy, PartialEq)]
+pub struct CollisionDamage {
+    /// Damage per N·s of the impulse over the threshold
+    pub factor: f32,
+
+    /// Impulses up to this deal no damage, so bumps are harmless
+    pub threshold: f32,
+}
+
+impl CollisionDamage {
+    pub fn damage(&self, impulse: f32) -> f32 {
+        (impulse - self.threshold).max(0.) * self.factor
+    }
+}
+
+/// Damage reduction of a damage kind
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct ArmorReduction {
+    /// Subtracted from every hit
+    pub flat: f32,
+
+    /// The ratio of the remaining damage which is blocked, from `0.0` to `1.0`
+    pub percent: f32,
+}
+
+impl ArmorReduction {
+    pub fn reduce(&self, amount: f32) -> f32 {
+        (amount - self.flat).max(0.) * (1. - self.percent.clamp(0., 1.))
+    }
+}
+
+/// Per damage kind reduction, applied after the shield and before Hp.
+/// Omitted kinds aren't reduced.
+#[derive(Serialize, Deserialize, Debug, Component, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Armor {
+    pub kinetic: ArmorReduction,
+    pub energy: ArmorReduction,
+    pub explosive: ArmorReduction,
+    pub collision: ArmorReduction,
+    pub melee: ArmorReduction,
+}
+
+impl Armor {
+    pub fn get(&self, kind: DamageKind) -> &ArmorReduction {
+        match kind {
+            DamageKind::Kinetic => &self.kinetic,
+            DamageKind::Energy => &self.energy,
+            DamageKind::Explosive => &self.explosive,
+            DamageKind::Collision => &self.collision,
+            DamageKind::Melee => &self.melee,
+        }
+    }
+
+    pub fn reduce(&self, kind: DamageKind, amount: f32) -> f32 {
+        self.get(kind).reduce(amount)
+    }
+}
+
+/// Per damage kind resistances, applied after Armor.
+/// `0.0` takes the full damage, `1.0` is immune, and a negative value is a weakness which takes more.
+#[derive(Serialize, Deserialize, Debug, Component, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub energy: f32,
+    pub explosive: f32,
+    pub collision: f32,
+    pub melee: f32,
+}
+
+impl Resistances {
+    pub fn get(&self, kind: DamageKind) -> f32 {
+        match kind {
+            DamageKind::Kinetic => self.kinetic,
+            DamageKind::Energy => self.energy,
+            DamageKind::Explosive => self.explosive,
+            DamageKind::Collision => self.collision,
+            DamageKind::Melee => self.melee,
+        }
+    }
+
+    pub fn reduce(&self, kind: DamageKind, amount: f32) -> f32 {
+        amount * (1. - self.get(kind).min(1.))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     /// GameMode's unit tests
@@ -164,6 +377,176 @@ mod tests {
             let entity: Entity = Entity::PLACEHOLDER; // A placeholder value
             let event: DeathMessage = DeathMessage::new(entity);
             assert_eq!(event.entity, entity);
+            assert_eq!(event.instigator, None);
+        }
+
+        /// killed_by method's unit test
+        #[test]
+        fn killed_by() {
+            let entity: Entity = Entity::from_raw_u32(1).unwrap();
+            let instigator: Entity = Entity::from_raw_u32(2).unwrap();
+            let event: DeathMessage = DeathMessage::killed_by(entity, Some(instigator));
+            assert_eq!(event.entity, entity);
+            assert_eq!(event.instigator, Some(instigator));
+        }
+    }
+
+    /// KillTallies's unit tests
+    mod kill_tallies {
+        use crate::{KillTallies, Tally};
+        use bevy::prelude::*;
+
+        /// record method's unit test
+        #[test]
+        fn record() {
+            let player: Entity = Entity::from_raw_u32(1).unwrap();
+            let target: Entity = Entity::from_raw_u32(2).unwrap();
+            let mut tallies: KillTallies = KillTallies::default();
+
+            tallies.record(target, Some(player));
+
+            assert_eq!(
+                tallies.get(player),
+                Tally {
+                    kills: 1,
+                    deaths: 0
+                }
+            );
+            assert_eq!(
+                tallies.get(target),
+                Tally {
+                    kills: 0,
+                    deaths: 1
+                }
+            );
+        }
+
+        /// A death without an instigator only counts the death
+        #[test]
+        fn record_without_instigator() {
+            let target: Entity = Entity::from_raw_u32(2).unwrap();
+            let mut tallies: KillTallies = KillTallies::default();
+
+            tallies.record(target, None);
+
+            assert_eq!(
+                tallies.get(target),
+                Tally {
+                    kills: 0,
+                    deaths: 1
+                }
+            );
+        }
+
+        /// A kill by oneself is not credited
+        #[test]
+        fn record_suicide() {
+            let player: Entity = Entity::from_raw_u32(1).unwrap();
+            let mut tallies: KillTallies = KillTallies::default();
+
+            tallies.record(player, Some(player));
+
+            assert_eq!(
+                tallies.get(player),
+                Tally {
+                    kills: 0,
+                    deaths: 1
+                }
+            );
+        }
+    }
+
+    /// Shield's unit tests
+    mod shield {
+        use crate::Shield;
+
+        /// The shield absorbs damage up to its rest, and the overflow goes through
+        #[test]
+        fn absorb() {
+            let mut shield: Shield = Shield::new(50., 10., 2.);
+
+            assert_eq!(shield.absorb(30.), 0.);
+            assert_eq!(shield.absorb(30.), 10.);
+            assert_eq!(shield.rest, 0.);
+        }
+
+        /// The shield regenerates only after the delay, up to its maximum
+        #[test]
+        fn regenerate() {
+            let mut shield: Shield = Shield::new(50., 10., 2.);
+            shield.absorb(40.);
+
+            shield.regenerate(1.);
+            assert_eq!(shield.rest, 10.);
+
+            shield.regenerate(1.);
+            assert_eq!(shield.rest, 20.);
+
+            shield.regenerate(10.);
+            assert_eq!(shield.rest, 50.);
+        }
+    }
+
+    /// Armor's unit tests
+    mod armor {
+        use crate::{Armor, ArmorReduction, DamageKind};
+
+        /// Flat reduction is applied before the percentage
+        #[test]
+        fn reduce() {
+            let armor: Armor = Armor {
+                kinetic: ArmorReduction {
+                    flat: 10.,
+                    percent: 0.5,
+                },
+                ..Default::default()
+            };
+
+            assert_eq!(armor.reduce(DamageKind::Kinetic, 30.), 10.);
+            assert_eq!(armor.reduce(DamageKind::Kinetic, 5.), 0.);
+        }
+
+        /// Each kind is reduced by its own reduction, and omitted kinds aren't reduced
+        #[test]
+        fn per_kind() {
+            let armor: Armor = Armor {
+                kinetic: ArmorReduction {
+                    flat: 10.,
+                    percent: 0.,
+                },
+                explosive: ArmorReduction {
+                    flat: 0.,
+                    percent: 0.5,
+                },
+                ..Default::default()
+            };
+
+            assert_eq!(armor.reduce(DamageKind::Kinetic, 30.), 20.);
+            assert_eq!(armor.reduce(DamageKind::Explosive, 30.), 15.);
+            assert_eq!(armor.reduce(DamageKind::Energy, 30.), 30.);
+        }
+    }
+
+    /// Resistances' unit tests
+    mod resistances {
+        use crate::{DamageKind, Resistances};
+
+        /// Each kind is reduced by its own resistance
+        #[test]
+        fn reduce() {
+            let resistances: Resistances = Resistances {
+                kinetic: 0.5,
+                energy: -0.5,
+                melee: 2.0,
+                ..Default::default()
+            };
+
+            assert_eq!(resistances.reduce(DamageKind::Kinetic, 10.), 5.);
+            assert_eq!(resistances.reduce(DamageKind::Energy, 10.), 15.);
+            assert_eq!(resistances.reduce(DamageKind::Explosive, 10.), 10.);
+
+            // Over 1.0 is immune, and never heals
+            assert_eq!(resistances.reduce(DamageKind::Melee, 10.), 0.);
         }
     }
 
@@ -0,0 +1,564 @@
+//! # Input recording & replay
+//!
+//! The recorder captures every frame's keyboard, mouse button, mouse motion & gamepad inputs into a file.
+//! The playback feeds them back into the input resources & gamepads, so every system reading them behaves as if a player is there.
+//! While it runs, live gamepads are held idle so they can't mix into the recorded inputs.
+
+use crate::input::ActionSystems;
+use bevy::{
+    input::{InputSystems, gamepad::GamepadInput, mouse::AccumulatedMouseMotion},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Inputs captured in a frame
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct InputFrame {
+    /// The elapsed time of this frame
+    pub delta: Duration,
+
+    /// Pressed keys
+    pub keys: Vec<KeyCode>,
+
+    /// Pressed mouse buttons
+    pub mouse_buttons: Vec<MouseButton>,
+
+    /// Accumulated mouse motion
+    pub mouse_motion: Vec2,
+
+    /// Connected gamepads
+    #[serde(default)]
+    pub gamepads: Vec<GamepadFrame>,
+}
+
+/// A gamepad's state captured in a frame
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct GamepadFrame {
+    /// Pressed buttons
+    pub buttons: Vec<GamepadButton>,
+
+    /// Analog values of the buttons, such as triggers
+    pub button_values: Vec<(GamepadButton, f32)>,
+
+    /// Positions of the sticks
+    pub axes: Vec<(GamepadAxis, f32)>,
+}
+
+impl GamepadFrame {
+    /// Captures the gamepad's state. Analog inputs at rest are left out, as they read the same as absent ones.
+    pub fn capture(gamepad: &Gamepad) -> Self {
+        let mut frame: Self = Self {
+            buttons: gamepad.get_pressed().copied().collect(),
+            ..default()
+        };
+
+        for (input, value) in gamepad
+            .analog()
+            .all_axes_and_values()
+            .filter(|(_, value)| *value != 0.0)
+        {
+            match *input {
+                GamepadInput::Button(button) => frame.button_values.push((button, value)),
+                GamepadInput::Axis(axis) => frame.axes.push((axis, value)),
+            }
+        }
+
+        frame
+    }
+
+    /// Makes the gamepad hold exactly this state
+    pub fn apply(&self, gamepad: &mut Gamepad) {
+        feed(gamepad.digital_mut(), &self.buttons);
+
+        let analog = gamepad.analog_mut();
+        let inputs: Vec<GamepadInput> = analog.all_axes().copied().collect();
+        for input in inputs {
+            analog.remove(input);
+        }
+
+        for (button, value) in &self.button_values {
+            analog.set(*button, *value);
+        }
+
+        for (axis, value) in &self.axes {
+            analog.set(*axis, *value);
+        }
+    }
+}
+
+/// Recorded inputs
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct InputRecording {
+    pub frames: Vec<InputFrame>,
+}
+
+impl InputRecording {
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let contents: String = std::fs::read_to_string(path)?;
+        let recording: Self = ron::from_str(&contents)?;
+
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let contents: String = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, contents)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Failed to read or write a recording: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse a recording: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+
+    #[error("Failed to serialize a recording: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// A plugin to record inputs into a file
+pub struct InputRecorderPlugin {
+    pub path: PathBuf,
+}
+
+/// The destination of InputRecording
+#[derive(Resource)]
+struct RecordingPath(PathBuf);
+
+impl Plugin for InputRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputRecording::default());
+        app.insert_resource(RecordingPath(self.path.clone()));
+        app.add_systems(PreUpdate, record_system.after(InputSystems));
+        app.add_systems(Last, save_recording_system);
+    }
+}
+
+/// A plugin to play recorded inputs back
+pub struct InputPlaybackPlugin {
+    pub recording: InputRecording,
+}
+
+/// The playback cursor of InputRecording
+#[derive(Resource, Default)]
+pub struct Playback {
+    pub frame: usize,
+}
+
+/// The TimeUpdateStrategy in place before the playback, which is restored when it ends
+#[derive(Resource, Default)]
+pub struct ResumedTimeUpdateStrategy(pub TimeUpdateStrategy);
+
+/// A gamepad spawned by the playback, standing for the recorded gamepad at the index
+#[derive(Component, Debug)]
+pub struct PlaybackGamepad(pub usize);
+
+impl Plugin for InputPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        // The first frame's clock must be set before any schedule runs
+        if let Some(first) = self.recording.frames.first() {
+            let resumed: TimeUpdateStrategy = app
+                .world_mut()
+                .remove_resource::<TimeUpdateStrategy>()
+                .unwrap_or_default();
+            app.insert_resource(ResumedTimeUpdateStrategy(resumed));
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(first.delta));
+        }
+
+        app.insert_resource(self.recording.clone());
+        app.insert_resource(Playback::default());
+        app.add_systems(
+            PreUpdate,
+            playback_system.after(InputSystems).before(ActionSystems),
+        );
+    }
+}
+
+/// Captures the inputs of the current frame
+pub fn record_system(
+    mut recording: ResMut<InputRecording>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    accumulated_mouse_motion: Res<AccumulatedMouseMotion>,
+    gamepads: Query<&Gamepad>,
+    time: Res<Time<Real>>,
+) {
+    recording.frames.push(InputFrame {
+        delta: time.delta(),
+        keys: keyboard.get_pressed().copied().collect(),
+        mouse_buttons: mouse_button.get_pressed().copied().collect(),
+        mouse_motion: accumulated_mouse_motion.delta,
+        gamepads: gamepads.iter().map(GamepadFrame::capture).collect(),
+    });
+}
+
+/// Writes InputRecording into the file when the app exits
+fn save_recording_system(
+    mut exit_reader: MessageReader<AppExit>,
+    recording: Res<InputRecording>,
+    path: Res<RecordingPath>,
+) {
+    if exit_reader.read().next().is_none() {
+        return;
+    }
+
+    match recording.save(&path.0) {
+        Ok(()) => info!("Saved the input recording to {}", path.0.display()),
+        Err(e) => error!("{e}"),
+    }
+}
+
+/// Feeds the current frame of InputRecording into the input resources.
+/// Each frame advances the clock by its recorded delta, so FixedUpdate runs the same fixed timesteps as the recorded session.
+/// Recorded gamepads are played by PlaybackGamepad entities, and live gamepads are held idle until the playback ends.
+/// When it ends, the clock goes back to the TimeUpdateStrategy which was in place before, e.g. the fixed steps of headless mode.
+#[allow(clippy::too_many_arguments)]
+pub fn playback_system(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    resumed: Option<ResMut<ResumedTimeUpdateStrategy>>,
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_button: ResMut<ButtonInput<MouseButton>>,
+    mut accumulated_mouse_motion: ResMut<AccumulatedMouseMotion>,
+    mut gamepads: Query<(Entity, &mut Gamepad, Option<&PlaybackGamepad>)>,
+    recording: Res<InputRecording>,
+) {
+    let Some(frame) = recording.frames.get(playback.frame) else {
+        for (entity, _, playback_gamepad) in gamepads.iter() {
+            if playback_gamepad.is_some() {
+                commands.entity(entity).despawn();
+            }
+        }
+        return;
+    };
+
+    feed(&mut keyboard, &frame.keys);
+    feed(&mut mouse_button, &frame.mouse_buttons);
+    accumulated_mouse_motion.delta = frame.mouse_motion;
+
+    let idle: GamepadFrame = GamepadFrame::default();
+    let mut played: Vec<usize> = Vec::new();
+    for (_, mut gamepad, playback_gamepad) in gamepads.iter_mut() {
+        let state: &GamepadFrame = match playback_gamepad {
+            Some(PlaybackGamepad(index)) => {
+                played.push(*index);
+                frame.gamepads.get(*index).unwrap_or(&idle)
+            }
+            None => &idle,
+        };
+        state.apply(&mut gamepad);
+    }
+
+    for (index, state) in frame.gamepads.iter().enumerate() {
+        if !played.contains(&index) {
+            let mut gamepad: Gamepad = Gamepad::default();
+            state.apply(&mut gamepad);
+            commands.spawn((PlaybackGamepad(index), gamepad));
+        }
+    }
+
+    playback.frame += 1;
+
+    match recording.frames.get(playback.frame) {
+        Some(next) => *time_update_strategy = TimeUpdateStrategy::ManualDuration(next.delta),
+        None => {
+            info!("Finished the input playback");
+            *time_update_strategy = resumed
+                .map(|mut resumed| std::mem::take(&mut resumed.0))
+                .unwrap_or_default();
+        }
+    }
+}
+
+/// Makes ButtonInput hold exactly the recorded buttons.
+/// Pressing or releasing them marks just_pressed & just_released as the live input does.
+fn feed<T>(input: &mut ButtonInput<T>, pressed: &[T])
+where
+    T: Copy + Eq + std::hash::Hash + Send + Sync + 'static,
+{
+    let released: Vec<T> = input
+        .get_pressed()
+        .filter(|v| !pressed.contains(v))
+        .copied()
+        .collect();
+
+    for v in released {
+        input.release(v);
+    }
+
+    for v in pressed {
+        input.press(*v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod input_recording {
+        use crate::replay::{GamepadFrame, InputFrame, InputRecording};
+        use bevy::prelude::*;
+        use std::{path::PathBuf, time::Duration};
+
+        #[test]
+        fn save_and_load() {
+            let recording: InputRecording = InputRecording {
+                frames: vec![InputFrame {
+                    delta: Duration::from_millis(16),
+                    keys: vec![KeyCode::KeyW, KeyCode::ShiftLeft],
+                    mouse_buttons: vec![MouseButton::Left],
+                    mouse_motion: Vec2::new(1.5, -2.0),
+                    gamepads: vec![GamepadFrame {
+                        buttons: vec![GamepadButton::South],
+                        button_values: vec![(GamepadButton::RightTrigger2, 0.5)],
+                        axes: vec![(GamepadAxis::LeftStickY, -0.25)],
+                    }],
+                }],
+            };
+
+            let path: PathBuf = std::env::temp_dir().join("spacerobo_replay_save_and_load.ron");
+            recording.save(&path).unwrap();
+            let loaded: InputRecording = InputRecording::load(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(loaded, recording);
+        }
+
+        /// Recordings made before gamepads were recorded still load
+        #[test]
+        fn without_gamepads() {
+            let contents: &str = "(frames: [(delta: (secs: 0, nanos: 16000000), keys: [KeyW], mouse_buttons: [], mouse_motion: (0.0, 0.0))])";
+            let recording: InputRecording = ron::from_str(contents).unwrap();
+
+            assert!(recording.frames[0].gamepads.is_empty());
+        }
+    }
+
+    mod plugins {
+        use crate::{
+            configs::GameConfigs,
+            input::{Action, ActionPlugin, ActionState},
+            replay::{
+                GamepadFrame, InputFrame, InputPlaybackPlugin, InputRecorderPlugin, InputRecording,
+                PlaybackGamepad,
+            },
+        };
+        use bevy::{input::InputPlugin, input::mouse::AccumulatedMouseMotion, prelude::*};
+        use std::time::Duration;
+
+        fn frame(keys: Vec<KeyCode>, mouse_motion: Vec2) -> InputFrame {
+            InputFrame {
+                delta: Duration::from_millis(10),
+                keys,
+                mouse_buttons: Vec::new(),
+                mouse_motion,
+                gamepads: Vec::new(),
+            }
+        }
+
+        /// A frame with a gamepad pushing the left stick forward
+        fn gamepad_frame(stick: f32) -> InputFrame {
+            InputFrame {
+                gamepads: vec![GamepadFrame {
+                    axes: vec![(GamepadAxis::LeftStickY, stick)],
+                    ..default()
+                }],
+                ..frame(Vec::new(), Vec2::ZERO)
+            }
+        }
+
+        #[test]
+        fn record() {
+            let mut app: App = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                InputPlugin,
+                InputRecorderPlugin {
+                    path: std::env::temp_dir().join("spacerobo_replay_record.ron"),
+                },
+            ));
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<KeyCode>>()
+                .press(KeyCode::KeyW);
+            app.update();
+
+            let recording = app.world().resource::<InputRecording>();
+            assert_eq!(recording.frames.len(), 1);
+            assert_eq!(recording.frames[0].keys, vec![KeyCode::KeyW]);
+        }
+
+        #[test]
+        fn record_gamepad() {
+            let mut app: App = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                InputPlugin,
+                InputRecorderPlugin {
+                    path: std::env::temp_dir().join("spacerobo_replay_record_gamepad.ron"),
+                },
+            ));
+
+            let mut gamepad: Gamepad = Gamepad::default();
+            gamepad.digital_mut().press(GamepadButton::South);
+            gamepad.analog_mut().set(GamepadAxis::LeftStickY, 0.75);
+            app.world_mut().spawn(gamepad);
+            app.update();
+
+            let recording = app.world().resource::<InputRecording>();
+            assert_eq!(
+                recording.frames[0].gamepads,
+                vec![GamepadFrame {
+                    buttons: vec![GamepadButton::South],
+                    button_values: Vec::new(),
+                    axes: vec![(GamepadAxis::LeftStickY, 0.75)],
+                }]
+            );
+        }
+
+        #[test]
+        fn playback() {
+            let recording: InputRecording = InputRecording {
+                frames: vec![
+                    frame(vec![KeyCode::KeyW], Vec2::new(3.0, 4.0)),
+                    frame(vec![KeyCode::KeyW], Vec2::ZERO),
+                    frame(Vec::new(), Vec2::ZERO),
+                ],
+            };
+
+            let mut app: App = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                InputPlugin,
+                InputPlaybackPlugin { recording },
+            ));
+
+            app.update();
+            let keyboard = app.world().resource::<ButtonInput<KeyCode>>();
+            assert!(keyboard.just_pressed(KeyCode::KeyW));
+            assert_eq!(
+                app.world().resource::<AccumulatedMouseMotion>().delta,
+                Vec2::new(3.0, 4.0)
+            );
+
+            app.update();
+            let keyboard = app.world().resource::<ButtonInput<KeyCode>>();
+            assert!(keyboard.pressed(KeyCode::KeyW));
+            assert!(!keyboard.just_pressed(KeyCode::KeyW));
+
+            app.update();
+            let keyboard = app.world().resource::<ButtonInput<KeyCode>>();
+            assert!(keyboard.just_released(KeyCode::KeyW));
+        }
+
+        /// Recorded gamepads drive the actions, while a live gamepad can't until the playback ends
+        #[test]
+        fn playback_gamepad() {
+            let recording: InputRecording = InputRecording {
+                frames: vec![gamepad_frame(1.0), gamepad_frame(0.0)],
+            };
+
+            let mut app: App = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                InputPlugin,
+                ActionPlugin,
+                InputPlaybackPlugin { recording },
+            ))
+            .insert_resource(GameConfigs::default());
+
+            // A live gamepad pushing the left stick backward
+            let mut gamepad: Gamepad = Gamepad::default();
+            gamepad.analog_mut().set(GamepadAxis::LeftStickY, -1.0);
+            let live: Entity = app.world_mut().spawn(gamepad).id();
+
+            let state = |app: &App| {
+                let state = app.world().resource::<ActionState>();
+                (state.value(Action::Forward), state.value(Action::Back))
+            };
+
+            app.update();
+            assert_eq!(state(&app), (1.0, 0.0));
+
+            app.update();
+            assert_eq!(state(&app), (0.0, 0.0));
+
+            // The playback has ended, so the playback gamepad leaves & the live one comes back
+            app.update();
+            app.world_mut()
+                .get_mut::<Gamepad>(live)
+                .unwrap()
+                .analog_mut()
+                .set(GamepadAxis::LeftStickY, -1.0);
+            app.update();
+            assert_eq!(state(&app), (0.0, 1.0));
+
+            let mut query = app.world_mut().query::<&PlaybackGamepad>();
+            assert_eq!(query.iter(app.world()).count(), 0);
+        }
+
+        /// Each frame advances the clock by its recorded delta
+        #[test]
+        fn playback_clock() {
+            let recording: InputRecording = InputRecording {
+                frames: vec![frame(Vec::new(), Vec2::ZERO); 3],
+            };
+
+            let mut app: App = App::new();
+            app.add_plugins((
+                MinimalPlugins,
+                InputPlugin,
+                InputPlaybackPlugin { recording },
+            ));
+
+            app.update();
+            app.update();
+            app.update();
+
+            assert_eq!(
+                app.world().resource::<Time<Real>>().delta(),
+                Duration::from_millis(10)
+            );
+        }
+
+        /// The clock goes back to the strategy which was in place before the playback, e.g. headless mode's fixed steps
+        #[test]
+        fn playback_clock_resumes() {
+            use bevy::time::TimeUpdateStrategy;
+
+            let recording: InputRecording = InputRecording {
+                frames: vec![frame(Vec::new(), Vec2::ZERO); 2],
+            };
+
+            let mut app: App = App::new();
+            app.add_plugins((MinimalPlugins, InputPlugin))
+                .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                    25,
+                )));
+            app.add_plugins(InputPlaybackPlugin { recording });
+
+            app.update();
+            app.update();
+            assert_eq!(
+                app.world().resource::<Time<Real>>().delta(),
+                Duration::from_millis(10)
+            );
+
+            app.update();
+            app.update();
+            assert_eq!(
+                app.world().resource::<Time<Real>>().delta(),
+                Duration::from_millis(25)
+            );
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! # Test fixtures shared by the crates' tests
+//!
+//! Enabled by the `testing` feature, which the other crates turn on in their dev-dependencies.
+
+use avian3d::prelude::*;
+use bevy::{app::PluginsState, prelude::*, scene::ScenePlugin, time::TimeUpdateStrategy};
+use std::time::Duration;
+
+/// An app with the physics and no gravity, advancing the clock by the time step per update
+pub fn physics_app(time_step: Duration) -> App {
+    let mut app: App = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        ScenePlugin,
+        TransformPlugin,
+        PhysicsPlugins::default(),
+    ));
+    app.init_asset::<Mesh>();
+    app.init_asset::<StandardMaterial>();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(time_step));
+    app.insert_resource(Gravity(Vec3::ZERO));
+
+    app
+}
+
+/// Updates the app for the ticks, finishing its plugins first as App::run does
+pub fn update(app: &mut App, ticks: usize) {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+
+    for _ in 0..ticks {
+        app.update();
+    }
+}
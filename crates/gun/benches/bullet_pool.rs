@@ -0,0 +1,111 @@
+//! # Bullet pool benchmark
+//!
+//! Fires full auto for a while, and reports the frame time with the entity & asset counts.
+//! Once the first bullets expire, every new bullet reuses a recycled one, so the counts must stay flat.
+//!
+//! ```sh
+//! cargo bench -p spacerobo_gun --bench bullet_pool
+//! ```
+
+use bevy::prelude::*;
+use spacerobo_commons::{
+    DamageKind,
+    testing::{physics_app, update},
+};
+use spacerobo_gun::{
+    bullet::{BulletPool, bullet_lifespan_system, recycle_observer},
+    definition::{ProjectileDefinition, RicochetDefinition},
+};
+use std::time::{Duration, Instant};
+
+/// The time which a frame advances the game clock
+const TIME_STEP: Duration = Duration::from_millis(16);
+
+/// Frames until the pool is warmed up, some lifetimes of the bullets
+const WARM_UP_FRAMES: usize = 600;
+
+/// Frames which are measured
+const MEASURED_FRAMES: u32 = 6000;
+
+/// The robo which fires
+#[derive(Resource)]
+struct Shooter(Entity);
+
+fn definition() -> ProjectileDefinition {
+    ProjectileDefinition {
+        speed: 300.0,
+        mass: 0.0125,
+        radius: 0.05,
+        size: 0.05,
+        pellets: 1,
+        spread: 0.0,
+        lifetime: 2.0,
+        range: 2000.0,
+        ricochet: RicochetDefinition::default(),
+        damage_kind: DamageKind::Kinetic,
+    }
+}
+
+/// Shoots a bullet every frame, faster than any weapon's full auto
+fn fire_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut pool: ResMut<BulletPool>,
+    shooter: Res<Shooter>,
+) {
+    pool.shoot(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &definition(),
+        Vec3::ZERO,
+        Vec3::NEG_Z * 300.0,
+        shooter.0,
+    );
+}
+
+/// Entities, meshes & materials
+fn counts(app: &mut App) -> (usize, usize, usize) {
+    let entities: usize = app.world_mut().query::<Entity>().iter(app.world()).count();
+    let meshes: usize = app.world().resource::<Assets<Mesh>>().len();
+    let materials: usize = app.world().resource::<Assets<StandardMaterial>>().len();
+
+    (entities, meshes, materials)
+}
+
+fn main() {
+    let mut app: App = physics_app(TIME_STEP);
+
+    let shooter: Entity = app.world_mut().spawn(Transform::default()).id();
+    app.insert_resource(Shooter(shooter));
+    app.init_resource::<BulletPool>();
+    app.add_observer(recycle_observer);
+    app.add_systems(Update, fire_system);
+    app.add_systems(FixedUpdate, bullet_lifespan_system);
+
+    update(&mut app, WARM_UP_FRAMES);
+    let (entities, meshes, materials) = counts(&mut app);
+
+    let start: Instant = Instant::now();
+    for _ in 0..MEASURED_FRAMES {
+        app.update();
+    }
+    let elapsed: Duration = start.elapsed();
+    let (later_entities, later_meshes, later_materials) = counts(&mut app);
+
+    println!(
+        "bullet_pool: {MEASURED_FRAMES} frames in {elapsed:?}, {:?} per frame",
+        elapsed / MEASURED_FRAMES
+    );
+    println!("  entities:  {entities} -> {later_entities}");
+    println!("  meshes:    {meshes} -> {later_meshes}");
+    println!("  materials: {materials} -> {later_materials}");
+
+    assert!(later_entities <= entities, "{later_entities} > {entities}");
+    assert!(later_meshes <= meshes, "{later_meshes} > {meshes}");
+    assert!(
+        later_materials <= materials,
+        "{later_materials} > {materials}"
+    );
+}
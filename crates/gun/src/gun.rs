@@ -1,12 +1,26 @@
 //! # Gun systems, components & etc...
 
 pub mod bullet;
+pub mod definition;
+pub mod hitscan;
+pub mod missile;
 pub mod select_fire;
+#[cfg(test)]
+mod testing;
 
-use self::select_fire::SelectFire;
+use self::{
+    definition::{HeatDefinition, MagazineDefinition, WeaponDefinition},
+    missile::LockOn,
+    select_fire::SelectFire,
+};
 use avian3d::prelude::*;
-use bevy::prelude::*;
-use spacerobo_commons::{DeathMessage, Hp};
+use bevy::{asset::LoadState, prelude::*};
+use serde::{Deserialize, Serialize};
+use spacerobo_commons::{
+    Controllable, Damage, DamageKind, Hp,
+    configs::player::RoboConfig,
+    input::{Action, ActionState},
+};
 use spacerobo_target::Common as CommonTarget;
 
 /// Gun component
@@ -14,11 +28,26 @@ use spacerobo_target::Common as CommonTarget;
 pub struct Gun {
     pub owner: Entity,
 
+    /// Weapon definition, describing what and how this gun shoots
+    pub definition: Handle<WeaponDefinition>,
+
     /// Select fire setting
     pub select_fire: SelectFire,
 
-    /// A interval settings and values
-    pub interval: Interval,
+    /// The rest of rounds in the current burst
+    pub burst_rest: u32,
+
+    /// The rest of interval until the next shot
+    pub interval_rest: f32,
+
+    /// Loaded & reserve ammo. A gun without a magazine never runs out of ammo.
+    pub magazine: Option<Magazine>,
+
+    /// Heat built up by shots
+    pub heat: Heat,
+
+    /// Lock-on progress for missiles
+    pub lock_on: LockOn,
 }
 
 impl Gun {
@@ -27,9 +56,8 @@ impl Gun {
         meshes: &mut Assets<Mesh>,
         materials: &mut Assets<StandardMaterial>,
         origin: Vec3,
+        definition: Handle<WeaponDefinition>,
     ) {
-        const DEFAULT_FIREMODE: SelectFire = SelectFire::Full;
-
         parent
             .spawn((
                 Transform::from_translation(origin),
@@ -37,13 +65,15 @@ impl Gun {
                 MeshMaterial3d(materials.add(Color::BLACK)),
                 (Gun {
                     owner: parent.target_entity(),
-                    select_fire: DEFAULT_FIREMODE,
-                    interval: Interval {
-                        limit: 0.1,
-                        rest: 0.0,
-                        amount: 0.01,
-                    },
+                    definition,
+                    select_fire: SelectFire::default(),
+                    burst_rest: 0,
+                    interval_rest: 0.0,
+                    magazine: None,
+                    heat: Heat::default(),
+                    lock_on: LockOn::default(),
                 }),
+                PendingDefinition,
                 ColliderConstructor::ConvexHullFromMesh,
                 CollisionEventsEnabled,
             ))
@@ -64,45 +94,327 @@ impl Gun {
 }
 
 impl Gun {
-    fn fullauto(&mut self) {
-        self.select_fire = SelectFire::Full;
+    /// Switches to the next fire mode in the list, cancelling the current burst
+    pub fn cycle_select_fire(&mut self, fire_modes: &[SelectFire]) {
+        if fire_modes.is_empty() {
+            return;
+        }
+
+        let next: usize = fire_modes
+            .iter()
+            .position(|mode| *mode == self.select_fire)
+            .map_or(0, |i| (i + 1) % fire_modes.len());
+
+        self.select_fire = fire_modes[next];
+        self.burst_rest = 0;
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.magazine.as_ref().is_some_and(Magazine::is_reloading)
     }
 
-    fn semiauto(&mut self) {
-        self.select_fire = SelectFire::Semi;
+    /// Takes a round for a shot. Returns false if the magazine is empty.
+    pub fn consume_round(&mut self) -> bool {
+        self.magazine.as_mut().is_none_or(Magazine::consume)
+    }
+
+    /// Takes a round if the gun can fire now, then starts the interval and heats the gun up
+    pub fn fire(&mut self, definition: &WeaponDefinition) -> Shot {
+        if self.interval_rest >= 0. || self.is_reloading() || self.heat.overheated {
+            return Shot::Blocked;
+        }
+
+        if !self.consume_round() {
+            return Shot::Empty;
+        }
+
+        self.interval_rest = definition.interval.limit;
+        if let Some(heat) = &definition.heat {
+            self.heat.add(heat);
+        }
+
+        Shot::Fired
     }
 }
 
-/// A interval settings and values
-#[derive(Default)]
+/// What a gun does when it is told to fire
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shot {
+    /// A round is fired
+    Fired,
+
+    /// The magazine is empty
+    Empty,
+
+    /// The gun is waiting for its interval, reloading or overheated
+    Blocked,
+}
+
+/// A interval settings
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
 pub struct Interval {
     /// The upper limit of interval
     pub limit: f32,
 
-    /// The rest of full-auto interval
-    pub rest: f32,
-
-    /// A number for rest_interval decrementing
+    /// A number for the rest of interval decrementing
     pub amount: f32,
 }
 
+/// Ammo of a gun
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Magazine {
+    /// The number of rounds a magazine holds
+    pub capacity: u32,
+
+    /// The number of rounds in the magazine
+    pub loaded: u32,
+
+    /// The number of rounds carried besides the magazine
+    pub reserve: u32,
+
+    /// Seconds until the reload completes. `None` means the gun is not reloading.
+    pub reload_rest: Option<f32>,
+}
+
+impl Magazine {
+    /// A full magazine
+    pub fn new(definition: &MagazineDefinition) -> Self {
+        Self {
+            capacity: definition.capacity,
+            loaded: definition.capacity,
+            reserve: definition.reserve,
+            reload_rest: None,
+        }
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reload_rest.is_some()
+    }
+
+    /// Takes a round from the magazine. Returns false if it is empty.
+    pub fn consume(&mut self) -> bool {
+        if self.loaded == 0 {
+            return false;
+        }
+
+        self.loaded -= 1;
+        true
+    }
+
+    /// Starts a reload. Returns false if the magazine is full, there is no reserve, or it is already reloading.
+    pub fn start_reload(&mut self, reload_time: f32) -> bool {
+        if self.is_reloading() || self.loaded >= self.capacity || self.reserve == 0 {
+            return false;
+        }
+
+        self.reload_rest = Some(reload_time);
+        true
+    }
+
+    /// Advances the reload by delta seconds, moving rounds from the reserve when it completes.
+    pub fn progress_reload(&mut self, delta: f32) {
+        let Some(rest) = self.reload_rest else {
+            return;
+        };
+
+        if rest - delta > 0. {
+            self.reload_rest = Some(rest - delta);
+            return;
+        }
+
+        let rounds: u32 = (self.capacity - self.loaded).min(self.reserve);
+        self.loaded += rounds;
+        self.reserve -= rounds;
+        self.reload_rest = None;
+    }
+}
+
+/// Heat of a gun
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Heat {
+    /// Current heat
+    pub value: f32,
+
+    /// The gun can't shoot until it cools down below the recovery threshold
+    pub overheated: bool,
+}
+
+impl Heat {
+    /// Adds the heat of a shot and locks the gun if it exceeds the threshold
+    pub fn add(&mut self, definition: &HeatDefinition) {
+        self.value += definition.per_shot;
+
+        if self.value > definition.threshold {
+            self.overheated = true;
+        }
+    }
+
+    /// Dissipates heat for delta seconds. Returns true when the lock is released.
+    pub fn dissipate(&mut self, definition: &HeatDefinition, delta: f32) -> bool {
+        self.value = (self.value - definition.dissipation * delta).max(0.);
+
+        if self.overheated && self.value < definition.recovery {
+            self.overheated = false;
+            return true;
+        }
+
+        false
+    }
+}
+
+/// A marker component for a gun whose weapon definition is not loaded yet
+#[derive(Component)]
+pub struct PendingDefinition;
+
 /// A marker component to know muzzle's transform
 #[derive(Component)]
 pub struct Muzzle;
 
+/// Applies the weapon definition to a gun when the definition is loaded.
+/// A definition which fails to load is reported, and replaced by the default weapon.
+pub fn gun_initialize_system(
+    mut commands: Commands,
+    mut gun_query: Query<(Entity, &mut Gun), With<PendingDefinition>>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, mut gun) in gun_query.iter_mut() {
+        if let Some(definition) = definitions.get(&gun.definition) {
+            debug!("{} is loaded", definition.name);
+
+            gun.select_fire = definition.initial_fire_mode();
+            gun.magazine = definition.magazine.as_ref().map(Magazine::new);
+            commands.entity(entity).remove::<PendingDefinition>();
+            continue;
+        }
+
+        let LoadState::Failed(e) = asset_server.load_state(&gun.definition) else {
+            continue;
+        };
+
+        let fallback: String = RoboConfig::default().weapon;
+        let path: String = gun
+            .definition
+            .path()
+            .map(ToString::to_string)
+            .unwrap_or_default();
+
+        if path == fallback {
+            // Nothing to fall back to, so the gun stays unarmed instead of waiting forever
+            error!("Could not load the default weapon definition: {e}");
+            commands.entity(entity).remove::<PendingDefinition>();
+        } else {
+            error!("Could not load the weapon definition: {e}. Falling back to {fallback}");
+            gun.definition = asset_server.load(fallback);
+        }
+    }
+}
+
 /// Gun cooling system.
-/// It controls full auto's shoot interval.
-pub fn gun_cooling_system(mut gun: Query<&mut Gun>) {
+/// It controls the shoot interval and dissipates heat.
+pub fn gun_cooling_system(
+    mut commands: Commands,
+    mut gun: Query<(&mut Gun, &GlobalTransform)>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time<Fixed>>,
+) {
+    for (mut gun, global_transform) in gun.iter_mut() {
+        let Some(definition) = definitions.get(&gun.definition) else {
+            continue;
+        };
+
+        gun.interval_rest -= definition.interval.amount;
+
+        if let Some(heat) = &definition.heat
+            && gun.heat.dissipate(heat, time.delta_secs())
+        {
+            debug!("{} has cooled down", definition.name);
+
+            if let Some(path) = &definition.cooldown_sound {
+                play_sound(
+                    &mut commands,
+                    &asset_server,
+                    path,
+                    global_transform.translation(),
+                );
+            }
+        }
+    }
+}
+
+/// Starts a reload of the player's guns when the Reload action is pressed
+pub fn reload_system(
+    mut commands: Commands,
+    mut gun_query: Query<(&mut Gun, &GlobalTransform), Without<PendingDefinition>>,
+    controllable_query: Query<(), With<Controllable>>,
+    actions: Res<ActionState>,
+    definitions: Res<Assets<WeaponDefinition>>,
+    asset_server: Res<AssetServer>,
+) {
+    if !actions.just_pressed(Action::Reload) {
+        return;
+    }
+
+    for (mut gun, global_transform) in gun_query.iter_mut() {
+        if !controllable_query.contains(gun.owner) {
+            continue;
+        }
+
+        let Some(definition) = definitions.get(&gun.definition) else {
+            continue;
+        };
+
+        let (Some(magazine), Some(magazine_definition)) = (&mut gun.magazine, &definition.magazine)
+        else {
+            continue;
+        };
+
+        if magazine.start_reload(magazine_definition.reload_time) {
+            debug!("Reloading {}", definition.name);
+
+            if let Some(path) = &definition.reload_sound {
+                play_sound(
+                    &mut commands,
+                    &asset_server,
+                    path,
+                    global_transform.translation(),
+                );
+            }
+        }
+    }
+}
+
+/// Gun reloading system.
+/// It advances reloads by the fixed timestep.
+pub fn gun_reloading_system(mut gun: Query<&mut Gun>, time: Res<Time>) {
     for mut gun in gun.iter_mut() {
-        gun.interval.rest -= gun.interval.amount;
+        if let Some(magazine) = &mut gun.magazine {
+            magazine.progress_reload(time.delta_secs());
+        }
     }
 }
 
+/// Plays a sound effect from an asset path
+pub(crate) fn play_sound(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    path: &str,
+    place: Vec3,
+) {
+    commands.spawn((
+        Transform::from_translation(place),
+        AudioPlayer::new(asset_server.load(path.to_string())),
+        PlaybackSettings::DESPAWN.with_spatial(false),
+    ));
+}
+
 pub fn gun_melee_damage_system(
+    mut commands: Commands,
     mut collision_event_reader: MessageReader<CollisionStart>,
-    mut death_message_writer: MessageWriter<DeathMessage>,
-    gun_query: Query<(), With<Gun>>,
-    mut target_query: Query<&mut Hp, With<CommonTarget>>,
+    gun_query: Query<&Gun>,
+    target_query: Query<(), (With<Hp>, With<CommonTarget>)>,
+    definitions: Res<Assets<WeaponDefinition>>,
 ) {
     for event in collision_event_reader.read() {
         debug!("Collision!!");
@@ -111,23 +423,270 @@ pub fn gun_melee_damage_system(
         let e2 = event.collider2;
 
         // Check which entity is the target when the gun collides
-        let target_entity = if gun_query.contains(e1) {
-            e2
-        } else if gun_query.contains(e2) {
-            e1
+        let (gun, target_entity) = if let Ok(gun) = gun_query.get(e1) {
+            (gun, e2)
+        } else if let Ok(gun) = gun_query.get(e2) {
+            (gun, e1)
         } else {
             continue;
         };
 
-        const HUGE_DAMAGE: f32 = 20000.0;
+        let Some(definition) = definitions.get(&gun.definition) else {
+            continue;
+        };
 
-        if let Ok(mut hp) = target_query.get_mut(target_entity) {
-            // Decrease HP
-            hp.rest -= HUGE_DAMAGE;
+        if target_query.contains(target_entity) {
+            commands.trigger(Damage {
+                target: target_entity,
+                amount: definition.melee_damage,
+                kind: DamageKind::Melee,
+                instigator: Some(gun.owner),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod gun {
+        use crate::gun::{
+            Gun, Heat, Interval, Magazine, Shot,
+            definition::{
+                HeatDefinition, HitscanDefinition, RecoilDefinition, WeaponDefinition, WeaponKind,
+            },
+            missile::LockOn,
+            select_fire::SelectFire,
+        };
+        use bevy::prelude::*;
+        use spacerobo_commons::DamageKind;
 
-            if hp.rest <= 0. {
-                death_message_writer.write(DeathMessage::new(target_entity));
+        /// The toggle cycles through the allowed fire modes only
+        #[test]
+        fn cycle_select_fire() {
+            let fire_modes: [SelectFire; 2] = [SelectFire::Semi, SelectFire::Burst(3)];
+            let mut gun: Gun = Gun {
+                owner: Entity::PLACEHOLDER,
+                definition: Handle::default(),
+                select_fire: SelectFire::Semi,
+                burst_rest: 0,
+                interval_rest: 0.0,
+                magazine: None,
+                heat: Heat::default(),
+                lock_on: LockOn::default(),
+            };
+
+            gun.cycle_select_fire(&fire_modes);
+            assert_eq!(gun.select_fire, SelectFire::Burst(3));
+
+            gun.cycle_select_fire(&fire_modes);
+            assert_eq!(gun.select_fire, SelectFire::Semi);
+
+            // A mode which isn't allowed falls back to the first one
+            gun.select_fire = SelectFire::Full;
+            gun.cycle_select_fire(&fire_modes);
+            assert_eq!(gun.select_fire, SelectFire::Semi);
+        }
+
+        /// A gun without a magazine, such as a heat-only energy weapon, never runs out nor reloads
+        #[test]
+        fn without_magazine() {
+            let mut gun: Gun = Gun {
+                owner: Entity::PLACEHOLDER,
+                definition: Handle::default(),
+                select_fire: SelectFire::Semi,
+                burst_rest: 0,
+                interval_rest: 0.0,
+                magazine: None,
+                heat: Heat::default(),
+                lock_on: LockOn::default(),
+            };
+
+            for _ in 0..1000 {
+                assert!(gun.consume_round());
             }
+            assert!(!gun.is_reloading());
+        }
+
+        /// A gun fires only when its interval is over and it is neither reloading nor overheated
+        #[test]
+        fn fire() {
+            let definition: WeaponDefinition = WeaponDefinition {
+                name: "Gun".to_string(),
+                fire_modes: vec![SelectFire::Semi],
+                interval: Interval {
+                    limit: 0.02,
+                    amount: 0.01,
+                },
+                kind: WeaponKind::Hitscan(HitscanDefinition {
+                    range: 100.0,
+                    damage: 10.0,
+                    beam_width: 0.05,
+                    beam_lifetime: 0.1,
+                    damage_kind: DamageKind::Energy,
+                }),
+                magazine: None,
+                recoil: RecoilDefinition::default(),
+                melee_damage: 0.0,
+                heat: Some(HeatDefinition {
+                    per_shot: 30.0,
+                    dissipation: 10.0,
+                    threshold: 100.0,
+                    recovery: 50.0,
+                }),
+                gunfire_sound: None,
+                empty_sound: None,
+                reload_sound: None,
+                cooldown_sound: None,
+            };
+            let mut gun: Gun = Gun {
+                owner: Entity::PLACEHOLDER,
+                definition: Handle::default(),
+                select_fire: SelectFire::Semi,
+                burst_rest: 0,
+                interval_rest: -1.0,
+                magazine: Some(Magazine {
+                    capacity: 2,
+                    loaded: 2,
+                    reserve: 0,
+                    reload_rest: None,
+                }),
+                heat: Heat::default(),
+                lock_on: LockOn::default(),
+            };
+
+            assert_eq!(gun.fire(&definition), Shot::Fired);
+            assert_eq!(gun.interval_rest, 0.02);
+            assert_eq!(gun.heat.value, 30.0);
+            assert_eq!(gun.fire(&definition), Shot::Blocked);
+
+            gun.interval_rest = -1.0;
+            gun.heat.overheated = true;
+            assert_eq!(gun.fire(&definition), Shot::Blocked);
+
+            gun.heat.overheated = false;
+            gun.magazine.as_mut().unwrap().reload_rest = Some(1.0);
+            assert_eq!(gun.fire(&definition), Shot::Blocked);
+
+            gun.magazine.as_mut().unwrap().reload_rest = None;
+            assert_eq!(gun.fire(&definition), Shot::Fired);
+
+            gun.interval_rest = -1.0;
+            assert_eq!(gun.fire(&definition), Shot::Empty);
+            assert_eq!(gun.magazine.as_ref().unwrap().loaded, 0);
+        }
+    }
+
+    mod magazine {
+        use crate::gun::{Magazine, definition::MagazineDefinition};
+
+        fn magazine() -> Magazine {
+            Magazine::new(&MagazineDefinition {
+                capacity: 3,
+                reserve: 4,
+                reload_time: 1.0,
+            })
+        }
+
+        /// Rounds are consumed until the magazine is empty
+        #[test]
+        fn consume() {
+            let mut magazine: Magazine = magazine();
+
+            assert!(magazine.consume());
+            assert!(magazine.consume());
+            assert!(magazine.consume());
+            assert!(!magazine.consume());
+            assert_eq!(magazine.loaded, 0);
+        }
+
+        /// A reload completes after its time and is limited by the reserve
+        #[test]
+        fn reload() {
+            let mut magazine: Magazine = magazine();
+            magazine.loaded = 0;
+
+            assert!(magazine.start_reload(1.0));
+            assert!(!magazine.start_reload(1.0));
+
+            magazine.progress_reload(0.5);
+            assert!(magazine.is_reloading());
+            assert_eq!(magazine.loaded, 0);
+
+            magazine.progress_reload(0.5);
+            assert!(!magazine.is_reloading());
+            assert_eq!((magazine.loaded, magazine.reserve), (3, 1));
+
+            magazine.loaded = 0;
+            assert!(magazine.start_reload(1.0));
+            magazine.progress_reload(1.0);
+            assert_eq!((magazine.loaded, magazine.reserve), (1, 0));
+        }
+
+        /// A full magazine or an empty reserve can't be reloaded
+        #[test]
+        fn reload_rejected() {
+            let mut magazine: Magazine = magazine();
+            assert!(!magazine.start_reload(1.0));
+
+            magazine.loaded = 0;
+            magazine.reserve = 0;
+            assert!(!magazine.start_reload(1.0));
+        }
+    }
+
+    mod heat {
+        use crate::gun::{Heat, definition::HeatDefinition};
+
+        fn definition() -> HeatDefinition {
+            HeatDefinition {
+                per_shot: 30.0,
+                dissipation: 10.0,
+                threshold: 100.0,
+                recovery: 50.0,
+            }
+        }
+
+        /// Exceeding the threshold locks the gun until it cools below the recovery threshold
+        #[test]
+        fn overheat() {
+            let definition: HeatDefinition = definition();
+            let mut heat: Heat = Heat::default();
+
+            for _ in 0..3 {
+                heat.add(&definition);
+            }
+            assert!(!heat.overheated);
+
+            heat.add(&definition);
+            assert!(heat.overheated);
+
+            // 120 -> 110 -> ... -> 60 is still locked, a second each
+            for _ in 0..6 {
+                assert!(!heat.dissipate(&definition, 1.0));
+            }
+            assert!(heat.overheated);
+
+            // 50 is not below the recovery threshold yet
+            assert!(!heat.dissipate(&definition, 1.0));
+            assert!(heat.dissipate(&definition, 1.0));
+            assert!(!heat.overheated);
+        }
+
+        /// Heat is dissipated by the second, and never goes below zero
+        #[test]
+        fn dissipate() {
+            let definition: HeatDefinition = definition();
+            let mut heat: Heat = Heat::default();
+
+            heat.add(&definition);
+            heat.dissipate(&definition, 0.5);
+            assert_eq!(heat.value, 25.0);
+
+            for _ in 0..10 {
+                heat.dissipate(&definition, 1.0);
+            }
+
+            assert_eq!(heat.value, 0.0);
         }
     }
 }
@@ -1,58 +1,94 @@
+use crate::gun::{
+    definition::{ProjectileDefinition, RicochetDefinition},
+    play_sound,
+};
 use avian3d::prelude::*;
-use bevy::prelude::*;
-use spacerobo_commons::{Bullet, Damage, Hp};
+use bevy::{platform::collections::HashMap, prelude::*};
+use spacerobo_commons::{Bullet, Damage, DamageKind, Hp, Pooled, Recycle};
 
-const BULLET_SIZE: f32 = 1. / 8.;
+/// Physics layers of bullets.
+/// Bullets don't collide with each other, so pellets of a shot can spawn at the same muzzle.
+#[derive(PhysicsLayer, Default)]
+enum BulletLayer {
+    #[default]
+    Default,
+    Bullet,
+}
 
 /// A marker component for a bullet shot by a Gun
 #[derive(Component)]
 pub struct Common {
     owner: Entity,
     bounce_count: usize,
+    ricochet: RicochetDefinition,
+    damage_kind: DamageKind,
 }
 
 impl Common {
-    pub fn new(owner: Entity) -> Self {
+    pub fn new(owner: Entity, definition: &ProjectileDefinition) -> Self {
         Self {
             owner,
             bounce_count: 0,
+            ricochet: definition.ricochet,
+            damage_kind: definition.damage_kind,
         }
     }
-}
 
-impl Bullet for Common {
-    fn shoot(
+    /// Spawns a bullet with the given mesh & material
+    #[allow(clippy::too_many_arguments)]
+    fn spawn(
         commands: &mut Commands,
-        meshes: &mut ResMut<Assets<Mesh>>,
-        materials: &mut ResMut<Assets<StandardMaterial>>,
+        mesh: Handle<Mesh>,
+        material: Handle<StandardMaterial>,
+        definition: &ProjectileDefinition,
+        origin: Vec3,
+        force: Vec3,
+        owner: Entity,
+    ) -> Entity {
+        commands
+            .spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material),
+                RigidBody::Dynamic,
+                SweptCcd::default(),
+                CollisionLayers::new(BulletLayer::Bullet, BulletLayer::Default),
+                CollisionEventsEnabled,
+                Self::flight(definition, origin, force, owner),
+            ))
+            .id()
+    }
+
+    /// Components which are reset on every shot
+    fn flight(
+        definition: &ProjectileDefinition,
         origin: Vec3,
         force: Vec3,
         owner: Entity,
-    ) {
-        commands.spawn((
+    ) -> impl Bundle {
+        (
             Transform::from_translation(origin),
-            Mesh3d(meshes.add(Sphere::new(BULLET_SIZE).mesh())),
-            MeshMaterial3d(materials.add(StandardMaterial {
-                base_color: Color::WHITE,
-                ..Default::default()
-            })),
-            RigidBody::Dynamic,
-            Collider::sphere(0.015625),
-            SweptCcd::default(),
+            Position(origin),
+            Collider::sphere(definition.radius),
             LinearVelocity(force),
-            Mass(3.0),
-            CollisionEventsEnabled,
-            Common::new(owner),
+            AngularVelocity::ZERO,
+            Mass(definition.mass),
+            Restitution::new(definition.ricochet.restitution)
+                .with_combine_rule(CoefficientCombine::Max),
+            Common::new(owner, definition),
             Hp::ammo(),
-        ));
+            Lifespan {
+                origin,
+                age: 0.,
+                lifetime: definition.lifetime,
+                range: definition.range,
+            },
+        )
     }
+}
 
+impl Bullet for Common {
     fn gunfire_sound(commands: &mut Commands, asset_server: &Res<AssetServer>, place: Vec3) {
-        commands.spawn((
-            Transform::from_translation(place),
-            AudioPlayer::new(asset_server.load("SE/shoot.ogg")),
-            PlaybackSettings::ONCE.with_spatial(false),
-        ));
+        play_sound(commands, asset_server, "SE/shoot.ogg", place);
     }
 
     fn owner(&self) -> Entity {
@@ -64,11 +100,146 @@ impl Bullet for Common {
     }
 }
 
-// Bullet specific collision system
+fn bullet_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::WHITE,
+        ..Default::default()
+    }
+}
+
+/// Limits of a bullet's flight
+#[derive(Component)]
+pub struct Lifespan {
+    origin: Vec3,
+
+    /// Seconds since the shot
+    age: f32,
+
+    /// Seconds until the bullet is removed
+    lifetime: f32,
+
+    /// The distance from the origin where the bullet is removed
+    range: f32,
+}
+
+/// Recycles bullet entities, and shares a mesh per bullet size & a material among bullets
+#[derive(Resource, Default)]
+pub struct BulletPool {
+    free: Vec<Entity>,
+    meshes: HashMap<u32, Handle<Mesh>>,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+impl BulletPool {
+    /// Shoots a bullet, reusing a recycled entity if there is one
+    #[allow(clippy::too_many_arguments)]
+    pub fn shoot(
+        &mut self,
+        commands: &mut Commands,
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        definition: &ProjectileDefinition,
+        origin: Vec3,
+        force: Vec3,
+        owner: Entity,
+    ) -> Entity {
+        let mesh: Handle<Mesh> = self
+            .meshes
+            .entry(definition.size.to_bits())
+            .or_insert_with(|| meshes.add(Sphere::new(definition.size).mesh()))
+            .clone();
+
+        match self.free.pop() {
+            Some(entity) => {
+                commands
+                    .entity(entity)
+                    .remove::<(RigidBodyDisabled, ColliderDisabled)>()
+                    .insert((
+                        Mesh3d(mesh),
+                        Visibility::Inherited,
+                        Common::flight(definition, origin, force, owner),
+                    ));
+
+                entity
+            }
+            None => {
+                let material: Handle<StandardMaterial> = self
+                    .material
+                    .get_or_insert_with(|| materials.add(bullet_material()))
+                    .clone();
+
+                let entity: Entity =
+                    Common::spawn(commands, mesh, material, definition, origin, force, owner);
+                commands.entity(entity).insert(Pooled);
+
+                entity
+            }
+        }
+    }
+
+    /// The number of entities waiting for reuse
+    pub fn free(&self) -> usize {
+        self.free.len()
+    }
+}
+
+/// Takes a pooled bullet back, hiding it from the world until it is shot again
+pub fn recycle_observer(
+    recycle: On<Recycle>,
+    mut commands: Commands,
+    mut pool: ResMut<BulletPool>,
+    bullet_query: Query<(), (With<Common>, With<Pooled>)>,
+) {
+    if !bullet_query.contains(recycle.entity) || pool.free.contains(&recycle.entity) {
+        return;
+    }
+
+    commands
+        .entity(recycle.entity)
+        .remove::<(Common, Hp, Lifespan)>()
+        .insert((
+            RigidBodyDisabled,
+            ColliderDisabled,
+            Visibility::Hidden,
+            LinearVelocity::ZERO,
+        ));
+    pool.free.push(recycle.entity);
+}
+
+/// Recycles a pooled bullet, or despawns it
+fn remove(commands: &mut Commands, entity: Entity, pooled: bool) {
+    if pooled {
+        commands.trigger(Recycle { entity });
+    } else {
+        commands.entity(entity).try_despawn();
+    }
+}
+
+/// Removes bullets which are over their lifetime or range
+pub fn bullet_lifespan_system(
+    mut commands: Commands,
+    mut bullet_query: Query<(Entity, &mut Lifespan, &Transform, Has<Pooled>)>,
+    time: Res<Time>,
+) {
+    for (entity, mut lifespan, transform, pooled) in bullet_query.iter_mut() {
+        lifespan.age += time.delta_secs();
+
+        if lifespan.age < lifespan.lifetime
+            && transform.translation.distance(lifespan.origin) < lifespan.range
+        {
+            continue;
+        }
+
+        remove(&mut commands, entity, pooled);
+    }
+}
+
+// Bullet specific collision system.
+// A bullet bounces off up to its max_bounces, with the damage scaled on every bounce, and is removed on the next hit.
 pub fn bullet_collision_system(
     mut commands: Commands,
     mut collision_event_reader: MessageReader<CollisionStart>,
-    mut bullet_query: Query<(&mut Common, &LinearVelocity, &Mass)>,
+    mut bullet_query: Query<(&mut Common, &LinearVelocity, &Mass, Has<Pooled>)>,
     other_query: Query<(Option<&LinearVelocity>, Option<&Mass>)>,
 ) {
     for event in collision_event_reader.read() {
@@ -76,7 +247,7 @@ pub fn bullet_collision_system(
         let e2 = event.collider2;
 
         let mut process_collision = |bullet_entity, other_entity| {
-            if let Ok((mut bullet, b_vel, b_mass)) = bullet_query.get_mut(bullet_entity) {
+            if let Ok((mut bullet, b_vel, b_mass, pooled)) = bullet_query.get_mut(bullet_entity) {
                 // Ignore owner collision if bounce_count is 0
                 if bullet.bounce_count == 0 && bullet.owner == other_entity {
                     return;
@@ -88,18 +259,22 @@ pub fn bullet_collision_system(
                     speed += o_vel.length();
                 }
 
-                let damage = speed * **b_mass;
+                let damage = speed * **b_mass * bullet.ricochet.damage_scale(bullet.bounce_count);
 
-                // Apply damage to the hit object and the bullet itself
+                // Apply damage to the hit object.
+                // The owner is credited with the hit object's death.
                 commands.trigger(Damage {
                     target: other_entity,
                     amount: damage,
-                });
-                commands.trigger(Damage {
-                    target: bullet_entity,
-                    amount: damage,
+                    kind: bullet.damage_kind,
+                    instigator: Some(bullet.owner),
                 });
 
+                // Remove the bullet when it runs out of bounces
+                if bullet.bounce_count >= bullet.ricochet.max_bounces {
+                    remove(&mut commands, bullet_entity, pooled);
+                }
+
                 // Increment bounce count
                 bullet.bounce_count += 1;
             }
@@ -109,3 +284,111 @@ pub fn bullet_collision_system(
         process_collision(e2, e1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    mod bullet_collision_system {
+        use crate::gun::{
+            bullet::{BulletPool, Common, bullet_collision_system, recycle_observer},
+            definition::{ProjectileDefinition, RicochetDefinition},
+            testing::{Hits, update},
+        };
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::{DamageKind, Hp};
+
+        /// An app with a bullet shot toward -Z at 50 m/s from the origin
+        fn app(ricochet: RicochetDefinition) -> App {
+            let mut app: App = crate::gun::testing::app();
+            app.insert_resource(Gravity(Vec3::ZERO));
+            app.init_resource::<BulletPool>();
+            app.add_observer(recycle_observer);
+            app.add_systems(Update, bullet_collision_system);
+
+            let definition: ProjectileDefinition = ProjectileDefinition {
+                speed: 50.0,
+                mass: 3.0,
+                radius: 0.05,
+                size: 0.05,
+                pellets: 1,
+                spread: 0.0,
+                lifetime: 5.0,
+                range: 2000.0,
+                ricochet,
+                damage_kind: DamageKind::Kinetic,
+            };
+            app.add_systems(
+                Startup,
+                move |mut commands: Commands,
+                      mut pool: ResMut<BulletPool>,
+                      mut meshes: ResMut<Assets<Mesh>>,
+                      mut materials: ResMut<Assets<StandardMaterial>>| {
+                    pool.shoot(
+                        &mut commands,
+                        &mut meshes,
+                        &mut materials,
+                        &definition,
+                        Vec3::ZERO,
+                        Vec3::NEG_Z * definition.speed,
+                        Entity::PLACEHOLDER,
+                    );
+                },
+            );
+
+            app
+        }
+
+        fn wall(app: &mut App, z: f32) -> Entity {
+            app.world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, z),
+                    RigidBody::Static,
+                    Collider::cuboid(10.0, 10.0, 1.0),
+                    Hp::robo(None),
+                ))
+                .id()
+        }
+
+        /// A bullet without ricochet settings is recycled on its first hit
+        #[test]
+        fn removed_on_first_hit() {
+            let mut app: App = app(RicochetDefinition::default());
+            let wall: Entity = wall(&mut app, -5.0);
+
+            update(&mut app, 30);
+
+            let hits: &Vec<(Entity, f32)> = &app.world().resource::<Hits>().0;
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].0, wall);
+            assert_eq!(app.world().resource::<BulletPool>().free(), 1);
+        }
+
+        /// A bullet bounces between walls, with less damage on the bounce, and is recycled after its last bounce
+        #[test]
+        fn ricochet() {
+            let mut app: App = app(RicochetDefinition {
+                restitution: 1.0,
+                max_bounces: 1,
+                damage_factor: 0.5,
+            });
+            let front: Entity = wall(&mut app, -5.0);
+            let back: Entity = wall(&mut app, 5.0);
+
+            // Bounced off the front wall
+            update(&mut app, 10);
+            let mut query = app.world_mut().query::<(&Common, &LinearVelocity)>();
+            let (bullet, velocity) = query.single(app.world()).unwrap();
+            assert_eq!(bullet.bounce_count, 1);
+            assert!(velocity.z > 0.0);
+
+            // Hit the back wall
+            update(&mut app, 50);
+            let hits: &Vec<(Entity, f32)> = &app.world().resource::<Hits>().0;
+            assert_eq!(hits.len(), 2);
+            assert_eq!(hits[0].0, front);
+            assert_eq!(hits[1].0, back);
+            assert!((hits[1].1 / hits[0].1 - 0.5).abs() < 0.1);
+            assert_eq!(app.world().resource::<BulletPool>().free(), 1);
+        }
+    }
+}
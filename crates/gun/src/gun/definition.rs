@@ -0,0 +1,455 @@
+//! # Weapon definitions
+//!
+//! Weapons are described in `*.weapon.ron` files and loaded as assets, so their balance can be tuned without recompiling.
+
+use super::{Interval, select_fire::SelectFire};
+use bevy::{
+    asset::{AssetLoader, LoadContext, io::Reader},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use spacerobo_commons::DamageKind;
+use thiserror::Error;
+
+/// The golden angle, used to scatter pellets evenly in a spread cone
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+/// A weapon definition, loaded from a `*.weapon.ron` file
+#[derive(Asset, TypePath, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeaponDefinition {
+    /// Weapon's name
+    pub name: String,
+
+    /// Select fire settings which the toggle key cycles through. The first one is used when the gun is spawned.
+    pub fire_modes: Vec<SelectFire>,
+
+    /// A interval settings
+    pub interval: Interval,
+
+    /// What the weapon shoots
+    pub kind: WeaponKind,
+
+    /// Magazine settings. The weapon never runs out of ammo if it is `None`, so heat alone can limit it.
+    #[serde(default)]
+    pub magazine: Option<MagazineDefinition>,
+
+    /// Recoil settings
+    #[serde(default)]
+    pub recoil: RecoilDefinition,
+
+    /// Melee damage, dealt when the gun itself hits a target
+    pub melee_damage: f32,
+
+    /// Heat settings. The weapon never overheats if it is `None`.
+    #[serde(default)]
+    pub heat: Option<HeatDefinition>,
+
+    /// An asset path of the gunfire sound. The bullet's own sound is used if it is `None`.
+    #[serde(default)]
+    pub gunfire_sound: Option<String>,
+
+    /// An asset path of the sound played when the trigger is pulled on an empty magazine
+    #[serde(default)]
+    pub empty_sound: Option<String>,
+
+    /// An asset path of the sound played when a reload starts
+    #[serde(default)]
+    pub reload_sound: Option<String>,
+
+    /// An asset path of the sound played when an overheated weapon has cooled down
+    #[serde(default)]
+    pub cooldown_sound: Option<String>,
+}
+
+/// Recoil settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecoilDefinition {
+    /// A scale of the impulse against the projectiles' momentum. `1.0` conserves momentum.
+    pub factor: f32,
+
+    /// Whether the impulse is applied at the muzzle, kicking the owner's rotation by the muzzle offset from its center of mass
+    #[serde(default)]
+    pub angular: bool,
+}
+
+impl std::default::Default for RecoilDefinition {
+    fn default() -> Self {
+        Self {
+            factor: 1.0,
+            angular: false,
+        }
+    }
+}
+
+/// Heat settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeatDefinition {
+    /// Heat added by a shot
+    pub per_shot: f32,
+
+    /// Heat dissipated per second
+    pub dissipation: f32,
+
+    /// The weapon is locked when its heat exceeds this
+    pub threshold: f32,
+
+    /// The lock is released when the heat falls below this
+    pub recovery: f32,
+}
+
+/// Magazine settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MagazineDefinition {
+    /// The number of rounds a magazine holds
+    pub capacity: u32,
+
+    /// The number of rounds carried besides the magazine
+    pub reserve: u32,
+
+    /// Seconds to take for a reload
+    pub reload_time: f32,
+}
+
+/// What a weapon shoots
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum WeaponKind {
+    /// Physical projectiles, shot as bullet::Common
+    Projectile(ProjectileDefinition),
+
+    /// An instant ray which hits the first thing on its way
+    Hitscan(HitscanDefinition),
+
+    /// Homing missiles, shot as missile::Missile
+    Missile(MissileDefinition),
+}
+
+/// Physical projectile settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectileDefinition {
+    /// Muzzle velocity, added to the owner's velocity
+    pub speed: f32,
+
+    /// Projectile's mass
+    pub mass: f32,
+
+    /// Collider's radius
+    pub radius: f32,
+
+    /// Mesh's radius
+    pub size: f32,
+
+    /// The number of projectiles per shot
+    #[serde(default = "ProjectileDefinition::default_pellets")]
+    pub pellets: u32,
+
+    /// The half angle of the spread cone, in radians
+    #[serde(default)]
+    pub spread: f32,
+
+    /// Seconds until the projectile is removed
+    #[serde(default = "ProjectileDefinition::default_lifetime")]
+    pub lifetime: f32,
+
+    /// The distance from the muzzle where the projectile is removed
+    #[serde(default = "ProjectileDefinition::default_range")]
+    pub range: f32,
+
+    /// Ricochet settings. The projectile is removed on its first hit by default.
+    #[serde(default)]
+    pub ricochet: RicochetDefinition,
+
+    /// The kind of the impact damage
+    #[serde(default)]
+    pub damage_kind: DamageKind,
+}
+
+/// Ricochet settings
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RicochetDefinition {
+    /// Coefficient of restitution. `0.0` doesn't bounce, `1.0` bounces without losing speed.
+    pub restitution: f32,
+
+    /// The number of bounces the projectile survives. It is removed on the next hit.
+    pub max_bounces: usize,
+
+    /// A scale of the damage applied on every bounce
+    #[serde(default = "RicochetDefinition::default_damage_factor")]
+    pub damage_factor: f32,
+}
+
+impl std::default::Default for RicochetDefinition {
+    fn default() -> Self {
+        Self {
+            restitution: 0.0,
+            max_bounces: 0,
+            damage_factor: Self::default_damage_factor(),
+        }
+    }
+}
+
+impl RicochetDefinition {
+    fn default_damage_factor() -> f32 {
+        1.0
+    }
+
+    /// Damage scale of a hit after the given number of bounces
+    pub fn damage_scale(&self, bounce_count: usize) -> f32 {
+        self.damage_factor.powi(bounce_count as i32)
+    }
+}
+
+/// Hitscan settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HitscanDefinition {
+    /// The maximum distance of the ray
+    pub range: f32,
+
+    /// Damage to the hit entity
+    pub damage: f32,
+
+    /// Beam visual's radius
+    pub beam_width: f32,
+
+    /// Seconds until the beam visual disappears
+    pub beam_lifetime: f32,
+
+    /// The kind of the damage
+    #[serde(default = "HitscanDefinition::default_damage_kind")]
+    pub damage_kind: DamageKind,
+}
+
+impl HitscanDefinition {
+    fn default_damage_kind() -> DamageKind {
+        DamageKind::Energy
+    }
+}
+
+/// Homing missile settings
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MissileDefinition {
+    /// Speed at the launch, added to the owner's velocity
+    pub launch_speed: f32,
+
+    /// Acceleration by the missile's own thrust, in m/s²
+    pub thrust: f32,
+
+    /// The upper limit of speed
+    pub max_speed: f32,
+
+    /// The upper limit of turning, in radians per second
+    pub turn_rate: f32,
+
+    /// Missile's mass
+    pub mass: f32,
+
+    /// Collider's radius
+    pub radius: f32,
+
+    /// Mesh's size
+    pub size: f32,
+
+    /// Missile's Hp, so it can be shot down
+    pub hp: f32,
+
+    /// Seconds until the missile detonates by itself
+    pub lifetime: f32,
+
+    /// Seconds to hold aim on a target to lock on
+    pub lock_time: f32,
+
+    /// The half angle of the lock-on cone from the view direction, in radians
+    pub lock_angle: f32,
+
+    /// The maximum distance to lock on
+    pub lock_range: f32,
+
+    /// The missile detonates when an Hp entity comes within this distance
+    pub proximity: f32,
+
+    /// Radius of the blast
+    pub blast_radius: f32,
+
+    /// Damage at the center of the blast
+    pub blast_damage: f32,
+
+    /// An asset path of the blast sound
+    #[serde(default)]
+    pub blast_sound: Option<String>,
+}
+
+impl MissileDefinition {
+    /// Blast damage, falling off linearly to zero at the blast radius
+    pub fn blast_damage_at(&self, distance: f32) -> f32 {
+        self.blast_damage * (1. - distance / self.blast_radius).clamp(0., 1.)
+    }
+}
+
+impl ProjectileDefinition {
+    fn default_pellets() -> u32 {
+        1
+    }
+
+    fn default_lifetime() -> f32 {
+        5.0
+    }
+
+    fn default_range() -> f32 {
+        2000.0
+    }
+
+    /// Directions of each pellet in the muzzle's local space, where forward is `Vec3::NEG_Z`.
+    /// Pellets are scattered on a sunflower pattern inside the spread cone, so the pattern is the same on every shot.
+    pub fn pellet_directions(&self) -> Vec<Vec3> {
+        (0..self.pellets)
+            .map(|i| {
+                let tilt: f32 = self.spread * (i as f32 / self.pellets as f32).sqrt();
+                let turn: f32 = i as f32 * GOLDEN_ANGLE;
+
+                Quat::from_rotation_z(turn) * Quat::from_rotation_x(tilt) * Vec3::NEG_Z
+            })
+            .collect()
+    }
+}
+
+impl WeaponDefinition {
+    /// Select fire setting when the gun is spawned
+    pub fn initial_fire_mode(&self) -> SelectFire {
+        self.fire_modes.first().copied().unwrap_or_default()
+    }
+}
+
+/// An asset loader for `*.weapon.ron` files
+#[derive(Default, TypePath)]
+pub struct WeaponDefinitionLoader;
+
+#[derive(Debug, Error)]
+pub enum WeaponDefinitionLoaderError {
+    #[error("Could not read a weapon definition: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Could not parse a weapon definition: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for WeaponDefinitionLoader {
+    type Asset = WeaponDefinition;
+    type Settings = ();
+    type Error = WeaponDefinitionLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+
+        let definition: WeaponDefinition = ron::de::from_bytes(&bytes)?;
+
+        Ok(definition)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["weapon.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod weapon_definition {
+        use crate::gun::definition::WeaponDefinition;
+        use std::path::PathBuf;
+
+        /// Every weapon definition shipped with the client must be parsed
+        #[test]
+        fn shipped_definitions() {
+            let dir: PathBuf =
+                PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../client/assets/weapons");
+            let mut count: usize = 0;
+
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path: PathBuf = entry.unwrap().path();
+                let contents: String = std::fs::read_to_string(&path).unwrap();
+
+                if let Err(e) = ron::from_str::<WeaponDefinition>(&contents) {
+                    panic!("{}: {e}", path.display());
+                }
+                count += 1;
+            }
+
+            assert!(count > 0);
+        }
+    }
+
+    mod projectile_definition {
+        use crate::gun::definition::{ProjectileDefinition, RicochetDefinition};
+        use bevy::prelude::*;
+        use spacerobo_commons::DamageKind;
+
+        fn projectile(pellets: u32, spread: f32) -> ProjectileDefinition {
+            ProjectileDefinition {
+                speed: 500.0,
+                mass: 3.0,
+                radius: 0.015625,
+                size: 0.125,
+                pellets,
+                spread,
+                lifetime: 5.0,
+                range: 2000.0,
+                ricochet: RicochetDefinition::default(),
+                damage_kind: DamageKind::Kinetic,
+            }
+        }
+
+        /// A single pellet goes straight forward
+        #[test]
+        fn single_pellet() {
+            let directions: Vec<Vec3> = projectile(1, 0.5).pellet_directions();
+
+            assert_eq!(directions.len(), 1);
+            assert!(directions[0].abs_diff_eq(Vec3::NEG_Z, 1e-6));
+        }
+
+        /// Every pellet stays inside the spread cone
+        #[test]
+        fn spread_cone() {
+            let spread: f32 = 0.1;
+            let directions: Vec<Vec3> = projectile(8, spread).pellet_directions();
+
+            assert_eq!(directions.len(), 8);
+            for direction in directions {
+                assert!(direction.is_normalized());
+                assert!(direction.angle_between(Vec3::NEG_Z) <= spread + 1e-6);
+            }
+        }
+    }
+
+    mod ricochet_definition {
+        use crate::gun::definition::RicochetDefinition;
+
+        /// Damage is scaled by the factor on every bounce
+        #[test]
+        fn damage_scale() {
+            let ricochet: RicochetDefinition = RicochetDefinition {
+                restitution: 0.8,
+                max_bounces: 3,
+                damage_factor: 0.5,
+            };
+
+            assert_eq!(ricochet.damage_scale(0), 1.0);
+            assert_eq!(ricochet.damage_scale(1), 0.5);
+            assert_eq!(ricochet.damage_scale(2), 0.25);
+        }
+
+        /// Omitted fields keep the damage & remove the projectile on its first hit
+        #[test]
+        fn default() {
+            let ricochet: RicochetDefinition =
+                ron::from_str("(restitution: 0.5, max_bounces: 2)").unwrap();
+
+            assert_eq!(ricochet.damage_scale(2), 1.0);
+            assert_eq!(RicochetDefinition::default().max_bounces, 0);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! # Hitscan systems, components & etc...
+
+use crate::gun::definition::HitscanDefinition;
+use avian3d::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
+use spacerobo_commons::{Damage, Hp};
+
+/// A short-lived beam visual of a hitscan shot
+#[derive(Component)]
+pub struct Beam {
+    timer: Timer,
+}
+
+/// Shares a mesh per beam width & a material among beams
+#[derive(Resource, Default)]
+pub struct BeamAssets {
+    meshes: HashMap<u32, Handle<Mesh>>,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+impl BeamAssets {
+    /// A cylinder of the width and a unit length, which is scaled to each beam's length
+    fn mesh(&mut self, meshes: &mut Assets<Mesh>, width: f32) -> Handle<Mesh> {
+        self.meshes
+            .entry(width.to_bits())
+            .or_insert_with(|| meshes.add(Cylinder::new(width, 1.)))
+            .clone()
+    }
+
+    fn material(&mut self, materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+        self.material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgb(0.5, 0.8, 1.0),
+                    emissive: LinearRgba::rgb(2.0, 4.0, 8.0),
+                    unlit: true,
+                    ..Default::default()
+                })
+            })
+            .clone()
+    }
+}
+
+/// Casts a ray from the muzzle and triggers Damage on the first hit entity, if it has Hp.
+/// The owner and its gun are excluded from the ray.
+#[allow(clippy::too_many_arguments)]
+pub fn fire(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    beam_assets: &mut BeamAssets,
+    spatial_query: &SpatialQuery,
+    hp_query: &Query<(), With<Hp>>,
+    definition: &HitscanDefinition,
+    muzzle: &GlobalTransform,
+    owner: Entity,
+    gun: Entity,
+) {
+    let origin: Vec3 = muzzle.translation();
+    let direction: Dir3 = muzzle.forward();
+    let filter: SpatialQueryFilter =
+        SpatialQueryFilter::default().with_excluded_entities([owner, gun]);
+
+    let distance: f32 =
+        match spatial_query.cast_ray(origin, direction, definition.range, true, &filter) {
+            Some(hit) => {
+                debug!("Hitscan hit {:?}", hit.entity);
+
+                if hp_query.contains(hit.entity) {
+                    commands.trigger(Damage {
+                        target: hit.entity,
+                        amount: definition.damage,
+                        kind: definition.damage_kind,
+                        instigator: Some(owner),
+                    });
+                }
+
+                hit.distance
+            }
+            None => definition.range,
+        };
+
+    // Beam visual, a cylinder from the muzzle to the hit point
+    commands.spawn((
+        Transform::from_translation(origin + direction * distance / 2.)
+            .with_rotation(Quat::from_rotation_arc(Vec3::Y, *direction))
+            .with_scale(Vec3::new(1., distance, 1.)),
+        Mesh3d(beam_assets.mesh(meshes, definition.beam_width)),
+        MeshMaterial3d(beam_assets.material(materials)),
+        Beam {
+            timer: Timer::from_seconds(definition.beam_lifetime, TimerMode::Once),
+        },
+    ));
+}
+
+/// Despawns beams when their lifetime is over
+pub fn beam_system(
+    mut commands: Commands,
+    mut beam_query: Query<(Entity, &mut Beam)>,
+    time: Res<Time>,
+) {
+    for (entity, mut beam) in beam_query.iter_mut() {
+        if beam.timer.tick(time.delta()).is_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod fire {
+        use crate::gun::{
+            definition::HitscanDefinition,
+            hitscan::{Beam, BeamAssets, fire},
+            testing::{Hits, update},
+        };
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::{DamageKind, Hp};
+
+        #[derive(Resource)]
+        struct Shooter {
+            owner: Entity,
+            gun: Entity,
+        }
+
+        fn shoot_system(
+            mut commands: Commands,
+            mut meshes: ResMut<Assets<Mesh>>,
+            mut materials: ResMut<Assets<StandardMaterial>>,
+            mut beam_assets: ResMut<BeamAssets>,
+            spatial_query: SpatialQuery,
+            hp_query: Query<(), With<Hp>>,
+            shooter: Res<Shooter>,
+        ) {
+            let definition: HitscanDefinition = HitscanDefinition {
+                range: 100.0,
+                damage: 1000.0,
+                beam_width: 0.05,
+                beam_lifetime: 0.2,
+                damage_kind: DamageKind::Energy,
+            };
+
+            fire(
+                &mut commands,
+                &mut meshes,
+                &mut materials,
+                &mut beam_assets,
+                &spatial_query,
+                &hp_query,
+                &definition,
+                &GlobalTransform::IDENTITY,
+                shooter.owner,
+                shooter.gun,
+            );
+        }
+
+        fn app() -> App {
+            let mut app: App = crate::gun::testing::app();
+            app.init_resource::<BeamAssets>();
+
+            // The muzzle is inside the owner & its gun
+            let owner: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    Hp::robo(None),
+                ))
+                .id();
+            let gun: Entity = app
+                .world_mut()
+                .spawn((Transform::default(), Collider::sphere(0.5), ChildOf(owner)))
+                .id();
+            app.insert_resource(Shooter { owner, gun });
+
+            app
+        }
+
+        /// The ray ignores the owner and damages the first hit Hp entity
+        #[test]
+        fn first_hit() {
+            let mut app: App = app();
+            let near: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, -10.0),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    Hp::robo(None),
+                ))
+                .id();
+            app.world_mut().spawn((
+                Transform::from_xyz(0.0, 0.0, -20.0),
+                RigidBody::Static,
+                Collider::sphere(1.0),
+                Hp::robo(None),
+            ));
+
+            // Let the physics know the colliders, then shoot
+            update(&mut app, 3);
+            app.add_systems(Update, shoot_system);
+            app.update();
+
+            assert_eq!(app.world().resource::<Hits>().targets(), vec![near]);
+
+            let mut query = app.world_mut().query::<&Beam>();
+            assert_eq!(query.iter(app.world()).count(), 1);
+        }
+
+        /// A shot without Hp entities on its way damages nothing
+        #[test]
+        fn miss() {
+            let mut app: App = app();
+            app.world_mut().spawn((
+                Transform::from_xyz(0.0, 10.0, 0.0),
+                RigidBody::Static,
+                Collider::sphere(1.0),
+                Hp::robo(None),
+            ));
+
+            update(&mut app, 3);
+            app.add_systems(Update, shoot_system);
+            app.update();
+
+            assert!(app.world().resource::<Hits>().0.is_empty());
+        }
+
+        /// Beams of any length share a mesh & a material
+        #[test]
+        fn shares_assets() {
+            let mut app: App = app();
+            let target: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, -10.0),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                ))
+                .id();
+
+            update(&mut app, 3);
+            app.add_systems(Update, shoot_system);
+            app.update();
+            let meshes: usize = app.world().resource::<Assets<Mesh>>().len();
+            let materials: usize = app.world().resource::<Assets<StandardMaterial>>().len();
+
+            // The next beam misses, so it is longer
+            app.world_mut().despawn(target);
+            update(&mut app, 3);
+
+            let mut query = app.world_mut().query::<&Beam>();
+            assert!(query.iter(app.world()).count() > 1);
+            assert_eq!(app.world().resource::<Assets<Mesh>>().len(), meshes);
+            assert_eq!(
+                app.world().resource::<Assets<StandardMaterial>>().len(),
+                materials
+            );
+        }
+    }
+}
@@ -0,0 +1,461 @@
+//! # Missile systems, components & etc...
+
+#![allow(clippy::type_complexity)]
+
+use crate::gun::{
+    Gun,
+    bullet::Common as CommonBullet,
+    definition::{MissileDefinition, WeaponDefinition, WeaponKind},
+    play_sound,
+};
+use avian3d::prelude::*;
+use bevy::{platform::collections::HashMap, prelude::*};
+use spacerobo_commons::{Bullet, Damage, DamageKind, Hp};
+
+/// A homing missile shot by a Gun
+#[derive(Component)]
+pub struct Missile {
+    owner: Entity,
+    definition: MissileDefinition,
+
+    /// Seconds since the launch
+    age: f32,
+}
+
+/// Shares a mesh per missile size & a material among missiles
+#[derive(Resource, Default)]
+pub struct MissileAssets {
+    meshes: HashMap<u32, Handle<Mesh>>,
+    material: Option<Handle<StandardMaterial>>,
+}
+
+impl MissileAssets {
+    fn mesh(&mut self, meshes: &mut Assets<Mesh>, size: f32) -> Handle<Mesh> {
+        self.meshes
+            .entry(size.to_bits())
+            .or_insert_with(|| meshes.add(Cuboid::new(size, size, size * 4.)))
+            .clone()
+    }
+
+    fn material(&mut self, materials: &mut Assets<StandardMaterial>) -> Handle<StandardMaterial> {
+        self.material
+            .get_or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: Color::srgb(0.9, 0.3, 0.1),
+                    ..Default::default()
+                })
+            })
+            .clone()
+    }
+}
+
+/// A target which a missile steers toward
+#[derive(Component)]
+pub struct Homing {
+    pub target: Entity,
+}
+
+impl Missile {
+    #[allow(clippy::too_many_arguments)]
+    pub fn shoot(
+        commands: &mut Commands,
+        meshes: &mut ResMut<Assets<Mesh>>,
+        materials: &mut ResMut<Assets<StandardMaterial>>,
+        assets: &mut MissileAssets,
+        definition: &MissileDefinition,
+        origin: Vec3,
+        force: Vec3,
+        owner: Entity,
+    ) -> Entity {
+        let heading: Vec3 = force.try_normalize().unwrap_or(Vec3::NEG_Z);
+
+        commands
+            .spawn((
+                Transform::from_translation(origin)
+                    .with_rotation(Quat::from_rotation_arc(Vec3::NEG_Z, heading)),
+                Mesh3d(assets.mesh(meshes, definition.size)),
+                MeshMaterial3d(assets.material(materials)),
+                RigidBody::Dynamic,
+                Collider::sphere(definition.radius),
+                SweptCcd::default(),
+                GravityScale(0.0),
+                LinearVelocity(force),
+                Mass(definition.mass),
+                CollisionEventsEnabled,
+                Missile {
+                    owner,
+                    definition: definition.clone(),
+                    age: 0.0,
+                },
+                Hp::new(definition.hp, None),
+            ))
+            .id()
+    }
+}
+
+impl Bullet for Missile {
+    fn gunfire_sound(commands: &mut Commands, asset_server: &Res<AssetServer>, place: Vec3) {
+        play_sound(commands, asset_server, "SE/shoot.ogg", place);
+    }
+
+    fn owner(&self) -> Entity {
+        self.owner
+    }
+
+    /// A missile detonates on its first contact, so it never bounces
+    fn bounce_count(&self) -> usize {
+        0
+    }
+}
+
+/// Lock-on progress of a gun
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LockOn {
+    /// The entity being aimed at
+    pub target: Option<Entity>,
+
+    /// Seconds the target has been held in aim
+    pub progress: f32,
+}
+
+impl LockOn {
+    /// Keeps holding the candidate, or restarts with a new one
+    pub fn update(&mut self, candidate: Option<Entity>, delta: f32) {
+        if candidate != self.target {
+            self.target = candidate;
+            self.progress = 0.;
+        } else if candidate.is_some() {
+            self.progress += delta;
+        }
+    }
+
+    /// The target, once it has been held for the lock time
+    pub fn locked(&self, lock_time: f32) -> Option<Entity> {
+        self.target.filter(|_| self.progress >= lock_time)
+    }
+}
+
+/// Turns the velocity toward the target by at most the turn rate, and accelerates it up to the max speed
+pub fn steer(
+    velocity: Vec3,
+    to_target: Option<Vec3>,
+    definition: &MissileDefinition,
+    delta: f32,
+) -> Vec3 {
+    let heading: Vec3 = velocity.try_normalize().unwrap_or(Vec3::NEG_Z);
+
+    let heading: Vec3 = match to_target.and_then(Vec3::try_normalize) {
+        Some(desired) => {
+            let angle: f32 = heading.angle_between(desired);
+            let max_turn: f32 = definition.turn_rate * delta;
+
+            if angle <= max_turn {
+                desired
+            } else {
+                Quat::IDENTITY.slerp(Quat::from_rotation_arc(heading, desired), max_turn / angle)
+                    * heading
+            }
+        }
+        None => heading,
+    };
+
+    let speed: f32 = (velocity.length() + definition.thrust * delta).min(definition.max_speed);
+
+    heading * speed
+}
+
+/// Locks on the Hp entity nearest to the owner's view direction, for guns armed with missiles
+pub fn lock_on_system(
+    mut gun_query: Query<&mut Gun>,
+    owner_query: Query<&GlobalTransform>,
+    candidate_query: Query<
+        (Entity, &GlobalTransform),
+        (With<Hp>, Without<CommonBullet>, Without<Missile>),
+    >,
+    definitions: Res<Assets<WeaponDefinition>>,
+    time: Res<Time>,
+) {
+    for mut gun in gun_query.iter_mut() {
+        let Some(WeaponKind::Missile(missile)) = definitions.get(&gun.definition).map(|d| &d.kind)
+        else {
+            continue;
+        };
+
+        let Ok(view) = owner_query.get(gun.owner) else {
+            continue;
+        };
+
+        let candidate: Option<Entity> = candidate_query
+            .iter()
+            .filter(|(entity, _)| *entity != gun.owner)
+            .filter_map(|(entity, transform)| {
+                let to_target: Vec3 = transform.translation() - view.translation();
+                let angle: f32 = view.forward().angle_between(to_target);
+
+                (to_target.length() <= missile.lock_range && angle <= missile.lock_angle)
+                    .then_some((entity, angle))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity);
+
+        gun.lock_on.update(candidate, time.delta_secs());
+    }
+}
+
+/// Steers missiles toward their targets
+pub fn missile_steering_system(
+    mut missile_query: Query<(
+        &mut Missile,
+        Option<&Homing>,
+        &GlobalTransform,
+        &mut LinearVelocity,
+        &mut Rotation,
+    )>,
+    target_query: Query<&GlobalTransform>,
+    time: Res<Time>,
+) {
+    for (mut missile, homing, transform, mut velocity, mut rotation) in missile_query.iter_mut() {
+        missile.age += time.delta_secs();
+
+        let to_target: Option<Vec3> = homing
+            .and_then(|homing| target_query.get(homing.target).ok())
+            .map(|target| target.translation() - transform.translation());
+
+        velocity.0 = steer(
+            velocity.0,
+            to_target,
+            &missile.definition,
+            time.delta_secs(),
+        );
+
+        if let Some(heading) = velocity.0.try_normalize() {
+            *rotation = Rotation(Quat::from_rotation_arc(Vec3::NEG_Z, heading));
+        }
+    }
+}
+
+/// Detonates missiles close to an Hp entity or at the end of their lifetime.
+/// The blast damages every Hp entity in its radius except the owner, falling off with the distance.
+pub fn missile_detonation_system(
+    mut commands: Commands,
+    missile_query: Query<(Entity, &Missile, &GlobalTransform)>,
+    hp_query: Query<&GlobalTransform, With<Hp>>,
+    trigger_query: Query<(), (With<Hp>, Without<CommonBullet>, Without<Missile>)>,
+    spatial_query: SpatialQuery,
+    asset_server: Res<AssetServer>,
+) {
+    for (entity, missile, transform) in missile_query.iter() {
+        let origin: Vec3 = transform.translation();
+        let definition: &MissileDefinition = &missile.definition;
+
+        let proximity_filter: SpatialQueryFilter =
+            SpatialQueryFilter::default().with_excluded_entities([entity, missile.owner]);
+        let close: bool = spatial_query
+            .shape_intersections(
+                &Collider::sphere(definition.proximity),
+                origin,
+                Quat::IDENTITY,
+                &proximity_filter,
+            )
+            .into_iter()
+            .any(|other| trigger_query.contains(other));
+
+        if !close && missile.age < definition.lifetime {
+            continue;
+        }
+
+        debug!("Missile {entity:?} detonated");
+
+        let blast_filter: SpatialQueryFilter =
+            SpatialQueryFilter::default().with_excluded_entities([entity, missile.owner]);
+        for other in spatial_query.shape_intersections(
+            &Collider::sphere(definition.blast_radius),
+            origin,
+            Quat::IDENTITY,
+            &blast_filter,
+        ) {
+            if let Ok(other_transform) = hp_query.get(other) {
+                commands.trigger(Damage {
+                    target: other,
+                    amount: definition
+                        .blast_damage_at(other_transform.translation().distance(origin)),
+                    kind: DamageKind::Explosive,
+                    instigator: Some(missile.owner),
+                });
+            }
+        }
+
+        if let Some(path) = &definition.blast_sound {
+            play_sound(&mut commands, &asset_server, path, origin);
+        }
+
+        commands.entity(entity).try_despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn definition() -> crate::gun::definition::MissileDefinition {
+        crate::gun::definition::MissileDefinition {
+            launch_speed: 30.0,
+            thrust: 100.0,
+            max_speed: 200.0,
+            turn_rate: 1.0,
+            mass: 10.0,
+            radius: 0.25,
+            size: 0.25,
+            hp: 20.0,
+            lifetime: 8.0,
+            lock_time: 1.0,
+            lock_angle: 0.2,
+            lock_range: 1000.0,
+            proximity: 3.0,
+            blast_radius: 10.0,
+            blast_damage: 300.0,
+            blast_sound: None,
+        }
+    }
+
+    mod lock_on {
+        use crate::gun::missile::LockOn;
+        use bevy::prelude::*;
+
+        /// Holding a target locks it after the lock time, and switching targets restarts
+        #[test]
+        fn update() {
+            let a: Entity = Entity::from_raw_u32(1).unwrap();
+            let b: Entity = Entity::from_raw_u32(2).unwrap();
+            let mut lock_on: LockOn = LockOn::default();
+
+            lock_on.update(Some(a), 0.5);
+            lock_on.update(Some(a), 0.5);
+            assert_eq!(lock_on.locked(1.0), None);
+
+            lock_on.update(Some(a), 0.5);
+            assert_eq!(lock_on.locked(1.0), Some(a));
+
+            lock_on.update(Some(b), 0.5);
+            assert_eq!(lock_on.locked(1.0), None);
+
+            lock_on.update(None, 0.5);
+            assert_eq!(lock_on, LockOn::default());
+        }
+    }
+
+    mod steer {
+        use crate::gun::missile::steer;
+        use bevy::prelude::*;
+
+        /// The heading turns by at most the turn rate
+        #[test]
+        fn turn_rate() {
+            let velocity: Vec3 = steer(
+                Vec3::NEG_Z * 100.0,
+                Some(Vec3::X),
+                &super::definition(),
+                0.5,
+            );
+
+            assert!((velocity.angle_between(Vec3::NEG_Z) - 0.5).abs() < 1e-4);
+        }
+
+        /// The speed is capped by the max speed
+        #[test]
+        fn max_speed() {
+            let velocity: Vec3 = steer(Vec3::NEG_Z * 190.0, None, &super::definition(), 1.0);
+
+            assert!(velocity.abs_diff_eq(Vec3::NEG_Z * 200.0, 1e-4));
+        }
+    }
+
+    mod missile_detonation_system {
+        use crate::gun::{
+            missile::{Missile, missile_detonation_system},
+            testing::{Hits, app, update},
+        };
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::Hp;
+
+        /// A missile close to a target detonates and damages it
+        #[test]
+        fn proximity() {
+            let mut app: App = app();
+
+            let owner: Entity = app.world_mut().spawn(Transform::default()).id();
+            let target: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, -50.0),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    Hp::robo(None),
+                ))
+                .id();
+            let missile: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, -47.5),
+                    RigidBody::Static,
+                    Collider::sphere(0.25),
+                    Missile {
+                        owner,
+                        definition: super::definition(),
+                        age: 0.0,
+                    },
+                    Hp::new(20.0, None),
+                ))
+                .id();
+
+            // Let the physics know the colliders, then detonate
+            update(&mut app, 3);
+            app.add_systems(Update, missile_detonation_system);
+            app.update();
+
+            assert_eq!(app.world().resource::<Hits>().targets(), vec![target]);
+            assert!(app.world().get_entity(missile).is_err());
+        }
+
+        /// A missile running out of lifetime next to its owner doesn't damage the owner
+        #[test]
+        fn spares_owner() {
+            let mut app: App = app();
+
+            let owner: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    Hp::robo(None),
+                ))
+                .id();
+            let bystander: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 9.0, 0.0),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    Hp::robo(None),
+                ))
+                .id();
+            app.world_mut().spawn((
+                Transform::from_xyz(0.0, 4.0, 0.0),
+                RigidBody::Static,
+                Collider::sphere(0.25),
+                Missile {
+                    owner,
+                    definition: super::definition(),
+                    age: super::definition().lifetime,
+                },
+                Hp::new(20.0, None),
+            ));
+
+            update(&mut app, 3);
+            app.add_systems(Update, missile_detonation_system);
+            app.update();
+
+            assert_eq!(app.world().resource::<Hits>().targets(), vec![bystander]);
+        }
+    }
+}
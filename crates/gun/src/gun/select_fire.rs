@@ -1,12 +1,21 @@
-#![allow(clippy::type_complexity)]
-
-use crate::gun::{Gun, Muzzle, bullet::Common};
-use avian3d::prelude::*;
-use bevy::prelude::*;
-use spacerobo_commons::Bullet;
+use crate::gun::{
+    Gun, Muzzle, PendingDefinition, Shot,
+    bullet::{BulletPool, Common},
+    definition::{WeaponDefinition, WeaponKind},
+    hitscan::{self, BeamAssets},
+    missile::{Homing, Missile, MissileAssets},
+    play_sound,
+};
+use avian3d::{dynamics::rigid_body::forces::ForcesItem, prelude::*};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use spacerobo_commons::{
+    Bullet, Controllable, Hp,
+    input::{Action, ActionState},
+};
 
 /// Select fire setting for Gun component
-#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum SelectFire {
     /// Semi auto
     #[default]
@@ -14,129 +23,563 @@ pub enum SelectFire {
 
     /// Full auto
     Full,
+
+    /// Shoots the given number of rounds per trigger pull
+    Burst(u32),
 }
 
-/// Semi auto
-pub fn semi_auto_system(
-    mut commands: Commands,
-    mut querys: (
-        Query<(&Gun, &ChildOf), With<Gun>>,
-        Query<&GlobalTransform, With<Muzzle>>,
-        Query<&LinearVelocity>,
+/// Guns which are ready to fire, with their owners & children
+type GunQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Gun,
+        &'static ChildOf,
+        &'static Children,
     ),
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    asset_server: Res<AssetServer>,
-) {
-    // Unpacking querys
-    let (ref mut gun_query, muzzle_query, parent_linear_query) = querys;
+    Without<PendingDefinition>,
+>;
 
-    for (gun, child_of) in gun_query.iter() {
-        if mouse.just_pressed(MouseButton::Left) && gun.select_fire == SelectFire::Semi {
-            debug!("Mouse Left clicked");
+/// What the fire systems need to shoot from a gun
+#[derive(SystemParam)]
+pub struct Shooting<'w, 's> {
+    commands: Commands<'w, 's>,
+    muzzle_query: Query<'w, 's, &'static GlobalTransform, With<Muzzle>>,
+    owner_forces_query: Query<'w, 's, Forces, With<Controllable>>,
+    hp_query: Query<'w, 's, (), With<Hp>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    asset_server: Res<'w, AssetServer>,
+    definitions: Res<'w, Assets<WeaponDefinition>>,
+    spatial_query: SpatialQuery<'w, 's>,
+    pool: ResMut<'w, BulletPool>,
+    beam_assets: ResMut<'w, BeamAssets>,
+    missile_assets: ResMut<'w, MissileAssets>,
+}
 
-            if child_of.parent() != gun.owner {
-                debug!("Full auto shooting is abandoned");
-                return;
-            }
+impl Shooting<'_, '_> {
+    /// Fires the gun if it can, shooting from each of its own muzzles.
+    /// `click` plays the empty sound when the magazine is empty.
+    fn fire(
+        &mut self,
+        gun_entity: Entity,
+        gun: &mut Gun,
+        child_of: &ChildOf,
+        children: &Children,
+        click: bool,
+    ) -> Shot {
+        if child_of.parent() != gun.owner {
+            debug!("Shooting is abandoned");
+            return Shot::Blocked;
+        }
+
+        let Some(definition) = self.definitions.get(&gun.definition) else {
+            return Shot::Blocked;
+        };
 
-            if let Ok(player_linear_velocity) = parent_linear_query.get(child_of.parent()) {
-                for global_transform in muzzle_query.iter() {
+        // Only the player's robo shoots by the Fire action
+        let Ok(mut owner_forces) = self.owner_forces_query.get_mut(gun.owner) else {
+            return Shot::Blocked;
+        };
+
+        let shot: Shot = gun.fire(definition);
+        match shot {
+            Shot::Fired => {
+                for muzzle in self.muzzle_query.iter_many(children) {
                     // Shoot!!
-                    let bullet_origin: Vec3 = global_transform.translation();
-                    let direction: Dir3 = global_transform.forward();
-                    const BULLET_FORCE: f32 = 500.0;
-                    let bullet_vector: Vec3 = direction * BULLET_FORCE + **player_linear_velocity;
-                    let bullet_owner: Entity = gun.owner;
-
-                    // ray_origin debugging by spawning a sphere
-                    Common::shoot(
-                        &mut commands,
-                        &mut meshes,
-                        &mut materials,
-                        bullet_origin,
-                        bullet_vector,
-                        bullet_owner,
+                    shoot(
+                        &mut self.commands,
+                        &mut self.meshes,
+                        &mut self.materials,
+                        &mut self.pool,
+                        &mut self.beam_assets,
+                        &mut self.missile_assets,
+                        &self.asset_server,
+                        &self.spatial_query,
+                        &self.hp_query,
+                        definition,
+                        muzzle,
+                        gun_entity,
+                        gun,
+                        &mut owner_forces,
                     );
-
-                    Common::gunfire_sound(&mut commands, &asset_server, bullet_origin);
                 }
             }
+            Shot::Empty if click => {
+                if let Some(muzzle) = self.muzzle_query.iter_many(children).next() {
+                    empty(&mut self.commands, &self.asset_server, definition, muzzle);
+                }
+            }
+            Shot::Empty | Shot::Blocked => (),
+        }
+
+        shot
+    }
+}
+
+/// Semi auto
+pub fn semi_auto_system(
+    mut gun_query: GunQuery,
+    actions: Res<ActionState>,
+    mut shooting: Shooting,
+) {
+    if !actions.just_pressed(Action::Fire) {
+        return;
+    }
+
+    debug!("Fire pressed");
+
+    for (gun_entity, mut gun, child_of, children) in gun_query.iter_mut() {
+        if gun.select_fire == SelectFire::Semi {
+            shooting.fire(gun_entity, &mut gun, child_of, children, true);
         }
     }
 }
 
 /// Full auto
 pub fn full_auto_system(
-    mut commands: Commands,
-    mut querys: (
-        Query<(&mut Gun, &ChildOf), With<Gun>>,
-        Query<&GlobalTransform, With<Muzzle>>,
-        Query<&LinearVelocity>,
-    ),
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    asset_server: Res<AssetServer>,
+    mut gun_query: GunQuery,
+    actions: Res<ActionState>,
+    mut shooting: Shooting,
 ) {
-    // Unpacking querys
-    let (ref mut gun_query, muzzle_query, parent_linear_query) = querys;
-
-    // Get muzzle's GlobalTransform
-    for global_transform in muzzle_query.iter() {
-        for (mut gun, child_of) in gun_query.iter_mut() {
-            if let Ok(player_linear_velocity) = parent_linear_query.get(child_of.parent())
-                && mouse.pressed(MouseButton::Left)
-                && gun.select_fire == SelectFire::Full
-            {
-                debug!("Mouse Left clicked");
-
-                if child_of.parent() != gun.owner {
-                    debug!("Full auto shooting is abandoned");
-                    return;
-                }
+    if !actions.pressed(Action::Fire) {
+        return;
+    }
 
-                if gun.interval.rest >= 0. {
-                    debug!("Full auto shoot aborted because of the gun's interval");
-                    return;
-                }
+    // Click once per trigger pull, not every frame
+    let click: bool = actions.just_pressed(Action::Fire);
 
-                // Full auto interval
-                gun.interval.rest = gun.interval.limit;
-
-                // Shoot!!
-                let bullet_origin: Vec3 = global_transform.translation();
-                let direction: Dir3 = global_transform.forward();
-                const BULLET_FORCE: f32 = 500.0;
-                let bullet_vector: Vec3 = direction * BULLET_FORCE + **player_linear_velocity;
-                let bullet_owner: Entity = gun.owner;
-
-                // ray_origin debugging by spawning a sphere
-                Common::shoot(
-                    &mut commands,
-                    &mut meshes,
-                    &mut materials,
-                    bullet_origin,
-                    bullet_vector,
-                    bullet_owner,
+    for (gun_entity, mut gun, child_of, children) in gun_query.iter_mut() {
+        if gun.select_fire == SelectFire::Full {
+            shooting.fire(gun_entity, &mut gun, child_of, children, click);
+        }
+    }
+}
+
+/// Burst
+pub fn burst_fire_system(
+    mut gun_query: GunQuery,
+    actions: Res<ActionState>,
+    mut shooting: Shooting,
+) {
+    for (gun_entity, mut gun, child_of, children) in gun_query.iter_mut() {
+        let SelectFire::Burst(rounds) = gun.select_fire else {
+            continue;
+        };
+
+        // A trigger pull starts a burst, which completes even if the trigger is released
+        if actions.just_pressed(Action::Fire) && gun.burst_rest == 0 {
+            debug!("Fire pressed");
+            gun.burst_rest = rounds;
+        }
+
+        if gun.burst_rest == 0 {
+            continue;
+        }
+
+        if gun.is_reloading() || gun.heat.overheated {
+            debug!("Burst aborted because the gun is reloading or overheated");
+            gun.burst_rest = 0;
+            continue;
+        }
+
+        match shooting.fire(gun_entity, &mut gun, child_of, children, true) {
+            Shot::Fired => gun.burst_rest -= 1,
+            Shot::Empty => gun.burst_rest = 0,
+            Shot::Blocked => (),
+        }
+    }
+}
+
+/// Shoots once from the muzzle as the weapon definition describes.
+/// The owner takes the recoil, an equal and opposite impulse of the projectiles' momentum.
+#[allow(clippy::too_many_arguments)]
+fn shoot(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    pool: &mut BulletPool,
+    beam_assets: &mut BeamAssets,
+    missile_assets: &mut MissileAssets,
+    asset_server: &Res<AssetServer>,
+    spatial_query: &SpatialQuery,
+    hp_query: &Query<(), With<Hp>>,
+    definition: &WeaponDefinition,
+    muzzle: &GlobalTransform,
+    gun_entity: Entity,
+    gun: &Gun,
+    owner_forces: &mut ForcesItem,
+) {
+    let owner: Entity = gun.owner;
+    let origin: Vec3 = muzzle.translation();
+    let rotation: Quat = muzzle.rotation();
+    let owner_velocity: Vec3 = owner_forces.linear_velocity();
+
+    // Momentum of the projectiles relative to the owner
+    let mut momentum: Vec3 = Vec3::ZERO;
+
+    match &definition.kind {
+        WeaponKind::Projectile(projectile) => {
+            for direction in projectile.pellet_directions() {
+                let muzzle_velocity: Vec3 = rotation * direction * projectile.speed;
+                momentum += muzzle_velocity * projectile.mass;
+
+                pool.shoot(
+                    commands,
+                    meshes,
+                    materials,
+                    projectile,
+                    origin,
+                    muzzle_velocity + owner_velocity,
+                    owner,
                 );
+            }
+        }
+        WeaponKind::Hitscan(hitscan) => {
+            hitscan::fire(
+                commands,
+                meshes,
+                materials,
+                beam_assets,
+                spatial_query,
+                hp_query,
+                hitscan,
+                muzzle,
+                owner,
+                gun_entity,
+            );
+        }
+        WeaponKind::Missile(missile) => {
+            let muzzle_velocity: Vec3 = rotation * Vec3::NEG_Z * missile.launch_speed;
+            momentum += muzzle_velocity * missile.mass;
+
+            let entity: Entity = Missile::shoot(
+                commands,
+                meshes,
+                materials,
+                missile_assets,
+                missile,
+                origin,
+                muzzle_velocity + owner_velocity,
+                owner,
+            );
 
-                Common::gunfire_sound(&mut commands, &asset_server, bullet_origin);
+            if let Some(target) = gun.lock_on.locked(missile.lock_time) {
+                commands.entity(entity).insert(Homing { target });
             }
         }
     }
+
+    // Recoil
+    let impulse: Vec3 = -momentum * definition.recoil.factor;
+    if definition.recoil.angular {
+        owner_forces.apply_linear_impulse_at_point(impulse, origin);
+    } else {
+        owner_forces.apply_linear_impulse(impulse);
+    }
+
+    match &definition.gunfire_sound {
+        Some(path) => play_sound(commands, asset_server, path, origin),
+        None => Common::gunfire_sound(commands, asset_server, origin),
+    }
+}
+
+/// Pulls the trigger on an empty magazine
+fn empty(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    definition: &WeaponDefinition,
+    muzzle: &GlobalTransform,
+) {
+    debug!("{} is empty", definition.name);
+
+    if let Some(path) = &definition.empty_sound {
+        play_sound(commands, asset_server, path, muzzle.translation());
+    }
 }
 
 /// Toggle gun's select fire.
-/// Full auto <---> Semi auto
-pub fn toggle_select_fire_system(mut gun: Query<&mut Gun>, keyboard: Res<ButtonInput<KeyCode>>) {
-    if keyboard.just_pressed(KeyCode::KeyT) {
-        let mut gun = gun.single_mut().unwrap();
-
-        match gun.select_fire {
-            SelectFire::Semi => gun.fullauto(),
-            SelectFire::Full => gun.semiauto(),
+/// It cycles through the fire modes of the weapon definition.
+pub fn toggle_select_fire_system(
+    mut gun_query: Query<&mut Gun, Without<PendingDefinition>>,
+    actions: Res<ActionState>,
+    definitions: Res<Assets<WeaponDefinition>>,
+) {
+    if actions.just_pressed(Action::ToggleFireMode) {
+        for mut gun in gun_query.iter_mut() {
+            if let Some(definition) = definitions.get(&gun.definition) {
+                gun.cycle_select_fire(&definition.fire_modes);
+                debug!("Select fire: {:?}", gun.select_fire);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod fire_systems {
+        use crate::gun::{
+            Gun, Heat, Interval, Magazine, Muzzle,
+            bullet::{BulletPool, Common},
+            definition::*,
+            gun_cooling_system,
+            hitscan::BeamAssets,
+            missile::{LockOn, MissileAssets},
+            select_fire::{SelectFire, burst_fire_system, full_auto_system, semi_auto_system},
+        };
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            Controllable, DamageKind,
+            configs::GameConfigs,
+            input::ActionPlugin,
+            testing::{physics_app, update},
+        };
+        use std::time::Duration;
+
+        /// A gun of the select fire, with a full magazine and no reserve
+        fn app(select_fire: SelectFire) -> App {
+            let mut app: App = physics_app(Duration::from_millis(16));
+            app.add_plugins(ActionPlugin);
+            app.insert_resource(GameConfigs::default());
+            app.init_asset::<AudioSource>();
+            app.init_asset::<WeaponDefinition>();
+            app.init_resource::<BulletPool>();
+            app.init_resource::<BeamAssets>();
+            app.init_resource::<MissileAssets>();
+            app.add_systems(
+                Update,
+                (
+                    (semi_auto_system, full_auto_system, burst_fire_system),
+                    gun_cooling_system,
+                )
+                    .chain(),
+            );
+
+            let definition: Handle<WeaponDefinition> = app
+                .world_mut()
+                .resource_mut::<Assets<WeaponDefinition>>()
+                .add(WeaponDefinition {
+                    name: "Gun".to_string(),
+                    fire_modes: vec![select_fire],
+                    interval: Interval {
+                        limit: 0.02,
+                        amount: 0.01,
+                    },
+                    kind: WeaponKind::Projectile(ProjectileDefinition {
+                        speed: 500.0,
+                        mass: 3.0,
+                        radius: 0.015625,
+                        size: 0.125,
+                        pellets: 1,
+                        spread: 0.0,
+                        lifetime: 5.0,
+                        range: 2000.0,
+                        ricochet: RicochetDefinition::default(),
+                        damage_kind: DamageKind::Kinetic,
+                    }),
+                    magazine: Some(MagazineDefinition {
+                        capacity: 30,
+                        reserve: 0,
+                        reload_time: 1.0,
+                    }),
+                    gunfire_sound: Some("shoot.ogg".to_string()),
+                    empty_sound: None,
+                    reload_sound: None,
+                    recoil: RecoilDefinition::default(),
+                    melee_damage: 0.0,
+                    heat: None,
+                    cooldown_sound: None,
+                });
+
+            let owner: Entity = app
+                .world_mut()
+                .spawn((
+                    Controllable,
+                    Transform::default(),
+                    RigidBody::Dynamic,
+                    Mass(5.0),
+                ))
+                .id();
+            let gun: Entity = app
+                .world_mut()
+                .spawn((
+                    Gun {
+                        owner,
+                        definition,
+                        select_fire,
+                        burst_rest: 0,
+                        interval_rest: -1.0,
+                        magazine: Some(Magazine {
+                            capacity: 30,
+                            loaded: 30,
+                            reserve: 0,
+                            reload_rest: None,
+                        }),
+                        heat: Heat::default(),
+                        lock_on: LockOn::default(),
+                    },
+                    ChildOf(owner),
+                    Transform::default(),
+                ))
+                .id();
+            app.world_mut()
+                .spawn((Transform::default(), Muzzle, ChildOf(gun)));
+
+            // Let the physics know the owner's mass
+            update(&mut app, 1);
+
+            app
+        }
+
+        fn bullets(app: &mut App) -> usize {
+            app.world_mut().query::<&Common>().iter(app.world()).count()
+        }
+
+        fn magazine(app: &mut App) -> Mut<'_, Magazine> {
+            app.world_mut()
+                .query::<&mut Gun>()
+                .single_mut(app.world_mut())
+                .unwrap()
+                .map_unchanged(|gun| gun.magazine.as_mut().unwrap())
+        }
+
+        fn pull_trigger(app: &mut App) {
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+            app.update();
+        }
+
+        /// Spawns another gun like the first one on the same owner, with its own muzzle
+        fn spawn_gun(app: &mut App) {
+            let gun: &Gun = app
+                .world_mut()
+                .query::<&Gun>()
+                .iter(app.world())
+                .next()
+                .unwrap();
+            let gun: Gun = Gun {
+                owner: gun.owner,
+                definition: gun.definition.clone(),
+                select_fire: gun.select_fire,
+                burst_rest: 0,
+                interval_rest: -1.0,
+                magazine: gun.magazine.clone(),
+                heat: Heat::default(),
+                lock_on: LockOn::default(),
+            };
+
+            let owner: Entity = gun.owner;
+            let gun: Entity = app
+                .world_mut()
+                .spawn((gun, ChildOf(owner), Transform::default()))
+                .id();
+            app.world_mut()
+                .spawn((Transform::default(), Muzzle, ChildOf(gun)));
+        }
+
+        /// Each gun shoots from its own muzzle only
+        #[test]
+        fn own_muzzles() {
+            for select_fire in [SelectFire::Semi, SelectFire::Full, SelectFire::Burst(3)] {
+                let mut app: App = app(select_fire);
+                spawn_gun(&mut app);
+
+                pull_trigger(&mut app);
+                assert_eq!(bullets(&mut app), 2, "{select_fire:?}");
+            }
+        }
+
+        /// Semi auto shoots nothing while the gun is reloading
+        #[test]
+        fn semi_reloading() {
+            let mut app: App = app(SelectFire::Semi);
+            magazine(&mut app).reload_rest = Some(1.0);
+
+            pull_trigger(&mut app);
+            assert_eq!(bullets(&mut app), 0);
+        }
+
+        /// Semi auto shoots nothing from an empty magazine
+        #[test]
+        fn semi_empty() {
+            let mut app: App = app(SelectFire::Semi);
+            magazine(&mut app).loaded = 0;
+
+            pull_trigger(&mut app);
+            assert_eq!(bullets(&mut app), 0);
+        }
+
+        /// Full auto shoots nothing while the gun is reloading, however long the trigger is held
+        #[test]
+        fn full_reloading() {
+            let mut app: App = app(SelectFire::Full);
+            magazine(&mut app).reload_rest = Some(1.0);
+
+            pull_trigger(&mut app);
+            for _ in 0..10 {
+                app.update();
+            }
+            assert_eq!(bullets(&mut app), 0);
+        }
+
+        /// Full auto stops when the magazine runs out
+        #[test]
+        fn full_empty() {
+            let mut app: App = app(SelectFire::Full);
+            magazine(&mut app).loaded = 2;
+
+            // The interval takes 3 ticks per round
+            pull_trigger(&mut app);
+            for _ in 0..20 {
+                app.update();
+            }
+            assert_eq!(bullets(&mut app), 2);
+            assert_eq!(magazine(&mut app).loaded, 0);
+        }
+
+        /// A burst completes after the trigger is released, spaced by the interval
+        #[test]
+        fn completes_after_release() {
+            let mut app: App = app(SelectFire::Burst(3));
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+            app.update();
+            assert_eq!(bullets(&mut app), 1);
+
+            let mut mouse = app.world_mut().resource_mut::<ButtonInput<MouseButton>>();
+            mouse.release(MouseButton::Left);
+            mouse.clear();
+
+            // The interval takes 3 ticks per round
+            for _ in 0..20 {
+                app.update();
+            }
+            assert_eq!(bullets(&mut app), 3);
+        }
+
+        /// The owner takes an equal and opposite impulse of the bullet's momentum
+        #[test]
+        fn recoil() {
+            let mut app: App = app(SelectFire::Burst(3));
+
+            app.world_mut()
+                .resource_mut::<ButtonInput<MouseButton>>()
+                .press(MouseButton::Left);
+            app.update();
+
+            let velocity: LinearVelocity = *app
+                .world_mut()
+                .query_filtered::<&LinearVelocity, (With<RigidBody>, Without<Common>)>()
+                .single(app.world())
+                .unwrap();
+
+            // 3.0 kg * 500 m/s / 5.0 kg, backwards from the muzzle
+            assert!(velocity.abs_diff_eq(Vec3::new(0.0, 0.0, 300.0), 1e-3));
         }
     }
 }
@@ -0,0 +1,28 @@
+//! # Test fixtures shared by the weapon tests
+
+use bevy::prelude::*;
+use spacerobo_commons::{Damage, testing::physics_app};
+
+pub use spacerobo_commons::testing::update;
+use std::time::Duration;
+
+/// Damaged entities & amounts, in order
+#[derive(Resource, Default)]
+pub struct Hits(pub Vec<(Entity, f32)>);
+
+impl Hits {
+    pub fn targets(&self) -> Vec<Entity> {
+        self.0.iter().map(|(target, _)| *target).collect()
+    }
+}
+
+/// An app with the physics, ticking 16 ms per update and recording every Damage into Hits
+pub fn app() -> App {
+    let mut app: App = physics_app(Duration::from_millis(16));
+    app.init_resource::<Hits>();
+    app.add_observer(|damage: On<Damage>, mut hits: ResMut<Hits>| {
+        hits.0.push((damage.target, damage.amount));
+    });
+
+    app
+}
@@ -5,16 +5,30 @@ use spacerobo_commons::GameMode;
 
 #[derive(Default)]
 pub struct GunPlugin;
-pub use gun::{Gun, Interval, Muzzle, bullet, gun_cooling_system, select_fire};
+pub use gun::{
+    Gun, Heat, Interval, Magazine, Muzzle, PendingDefinition, Shot, bullet, definition,
+    gun_cooling_system, hitscan, missile, select_fire,
+};
 
 impl Plugin for GunPlugin {
     fn build(&self, app: &mut App) {
+        app.init_asset::<gun::definition::WeaponDefinition>();
+        app.init_asset_loader::<gun::definition::WeaponDefinitionLoader>();
+        app.init_resource::<gun::bullet::BulletPool>();
+        app.init_resource::<gun::hitscan::BeamAssets>();
+        app.init_resource::<gun::missile::MissileAssets>();
+        app.add_observer(gun::bullet::recycle_observer);
+
         app.add_systems(
             Update,
             (
+                gun::gun_initialize_system,
                 gun::select_fire::toggle_select_fire_system,
+                gun::reload_system,
                 gun::gun_melee_damage_system,
                 gun::bullet::bullet_collision_system,
+                gun::hitscan::beam_system,
+                gun::missile::lock_on_system,
             )
                 .run_if(in_state(GameMode::InGame)),
         );
@@ -24,6 +38,7 @@ impl Plugin for GunPlugin {
             (
                 gun::select_fire::full_auto_system,
                 gun::select_fire::semi_auto_system,
+                gun::select_fire::burst_fire_system,
             )
                 .run_if(in_state(GameMode::InGame))
                 .after(bevy::transform::TransformSystems::Propagate),
@@ -31,7 +46,17 @@ impl Plugin for GunPlugin {
 
         app.add_systems(
             FixedUpdate,
-            (gun::gun_cooling_system).run_if(in_state(GameMode::InGame)),
+            (
+                gun::gun_cooling_system,
+                gun::gun_reloading_system,
+                gun::bullet::bullet_lifespan_system,
+                (
+                    gun::missile::missile_steering_system,
+                    gun::missile::missile_detonation_system,
+                )
+                    .chain(),
+            )
+                .run_if(in_state(GameMode::InGame)),
         );
     }
 }
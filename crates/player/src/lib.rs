@@ -5,7 +5,9 @@ pub mod ui;
 use avian3d::prelude::*;
 use bevy::prelude::*;
 use spacerobo_commons::{
-    Controllable, DeathMessage, GameMode, Hp, KillCounter, Player, configs::GameConfigs,
+    CollisionDamage, Controllable, DeathMessage, Fuel, GameMode, Hp, KillCounter, Player, Shield,
+    configs::{GameConfigs, player::RoboConfig},
+    input::{Action, ActionState},
 };
 use spacerobo_gun::{Gun, GunPlugin};
 
@@ -13,6 +15,14 @@ use spacerobo_gun::{Gun, GunPlugin};
 #[derive(Component)]
 pub struct Common;
 
+impl Common {
+    /// How much ramming hurts the robo
+    pub const COLLISION_DAMAGE: CollisionDamage = CollisionDamage {
+        factor: 0.5,
+        threshold: 25.0,
+    };
+}
+
 impl Player for Common {
     fn spawn(
         commands: &mut Commands,
@@ -20,6 +30,7 @@ impl Player for Common {
         materials: &mut ResMut<Assets<StandardMaterial>>,
         kill_counter: &mut ResMut<KillCounter>,
         asset_server: Res<AssetServer>,
+        game_configs: &GameConfigs,
     ) {
         // Reset KillCounter
         kill_counter.reset();
@@ -27,7 +38,7 @@ impl Player for Common {
         let gap = 4.0;
 
         // Camera
-        commands
+        let player: Entity = commands
             .spawn((
                 DespawnOnExit(GameMode::InGame),
                 Camera3d::default(),
@@ -39,16 +50,43 @@ impl Player for Common {
                 AngularVelocity(Vec3::ZERO),
                 SpatialListener::new(gap),
                 Hp::robo(Some(asset_server.load("SE/kill.ogg"))),
+                Self::COLLISION_DAMAGE,
+                // Ramming is found by the robo's collision events
+                CollisionEventsEnabled,
                 Controllable,
                 Common,
             ))
             // Gun
             .with_children(|parent| {
                 let origin = Vec3::new(1.0, -1.0, -3.0);
-                Gun::spawn_as_child(parent, meshes, materials, origin);
+                let definition = asset_server.load(game_configs.player.robo.weapon.clone());
+                Gun::spawn_as_child(parent, meshes, materials, origin, definition);
 
                 debug!("Gun's parent.target_entity(): {:?}", parent.target_entity());
-            });
+            })
+            .id();
+
+        // Optional protections
+        let robo: &RoboConfig = &game_configs.player.robo;
+        if let Some(shield) = &robo.shield {
+            commands.entity(player).insert(Shield::new(
+                shield.capacity,
+                shield.regeneration,
+                shield.delay,
+            ));
+        }
+        if let Some(armor) = robo.armor {
+            commands.entity(player).insert(armor);
+        }
+        commands.entity(player).insert((
+            robo.resistances,
+            robo.flight_assist,
+            Fuel::new(
+                robo.fuel.capacity,
+                robo.fuel.regeneration,
+                robo.fuel.consumption,
+            ),
+        ));
     }
 }
 
@@ -74,6 +112,7 @@ pub fn setup_system(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut kill_counter: ResMut<KillCounter>,
     asset_server: Res<AssetServer>,
+    game_configs: Res<GameConfigs>,
 ) {
     Common::spawn(
         &mut commands,
@@ -81,6 +120,7 @@ pub fn setup_system(
         &mut materials,
         &mut kill_counter,
         asset_server,
+        &game_configs,
     );
 }
 
@@ -92,14 +132,14 @@ pub fn respawn_system(
     mut kill_counter: ResMut<KillCounter>,
     query: Query<&Common>,
     game_configs: Res<GameConfigs>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     asset_server: Res<AssetServer>,
 ) {
     if !query.is_empty() {
         return;
     }
 
-    if keyboard.just_pressed(game_configs.player.keyboard.respawn) {
+    if actions.just_pressed(Action::Respawn) {
         info!("Respawning player...");
 
         Common::spawn(
@@ -108,6 +148,7 @@ pub fn respawn_system(
             &mut materials,
             &mut kill_counter,
             asset_server,
+            &game_configs,
         );
     }
 }
@@ -4,7 +4,11 @@
 
 use super::Common as PlayerCommon;
 use bevy::prelude::*;
-use spacerobo_commons::{GameMode, Hp, KillCounter};
+use spacerobo_commons::{FlightAssist, Fuel, GameMode, Hp, KillCounter};
+use spacerobo_gun::{
+    Gun,
+    definition::{WeaponDefinition, WeaponKind},
+};
 
 #[derive(Component)]
 pub struct HeadingIndicator;
@@ -18,6 +22,18 @@ pub struct KillCounterUI;
 #[derive(Component)]
 pub struct HpUI;
 
+#[derive(Component)]
+pub struct FuelUI;
+
+#[derive(Component)]
+pub struct FlightAssistUI;
+
+#[derive(Component)]
+pub struct AmmoUI;
+
+#[derive(Component)]
+pub struct LockOnUI;
+
 pub fn setup_system(mut commands: Commands) {
     // Heading Indicator
     commands
@@ -53,6 +69,38 @@ pub fn setup_system(mut commands: Commands) {
             }),
             HpUI,
         ))
+        .with_child((
+            TextSpan::default(),
+            (TextFont {
+                font_size: 21.0,
+                ..default()
+            }),
+            FuelUI,
+        ))
+        .with_child((
+            TextSpan::default(),
+            (TextFont {
+                font_size: 21.0,
+                ..default()
+            }),
+            FlightAssistUI,
+        ))
+        .with_child((
+            TextSpan::default(),
+            (TextFont {
+                font_size: 21.0,
+                ..default()
+            }),
+            AmmoUI,
+        ))
+        .with_child((
+            TextSpan::default(),
+            (TextFont {
+                font_size: 21.0,
+                ..default()
+            }),
+            LockOnUI,
+        ))
         .with_child((
             TextSpan::default(),
             (TextFont {
@@ -69,11 +117,26 @@ pub fn update_system(
         Query<&mut TextSpan, With<CoordinatesIndicator>>,
         Query<&mut TextSpan, With<HpUI>>,
         Query<&mut TextSpan, With<KillCounterUI>>,
+        Query<&mut TextSpan, With<AmmoUI>>,
+        Query<&mut TextSpan, With<LockOnUI>>,
+        Query<&mut TextSpan, With<FuelUI>>,
+        Query<&mut TextSpan, With<FlightAssistUI>>,
     )>,
-    player_query: Query<(&Transform, &Hp), With<PlayerCommon>>,
+    player_query: Query<
+        (
+            Entity,
+            &Transform,
+            &Hp,
+            Option<&Fuel>,
+            Option<&FlightAssist>,
+        ),
+        With<PlayerCommon>,
+    >,
+    gun_query: Query<&Gun>,
     kill_counter: Res<KillCounter>,
+    definitions: Res<Assets<WeaponDefinition>>,
 ) {
-    for (transform, hp) in player_query.iter() {
+    for (entity, transform, hp, fuel, flight_assist) in player_query.iter() {
         for mut span in &mut spans.p0() {
             let rot: Vec3 = transform.rotation.xyz();
             **span = format!("({rot:.2})\n");
@@ -86,6 +149,53 @@ pub fn update_system(
         for mut span in &mut spans.p2() {
             **span = format!("Hp: {:.2}/{:.2}\n", hp.rest, hp.maximum);
         }
+
+        for mut span in &mut spans.p6() {
+            **span = match fuel {
+                Some(fuel) => format!("Fuel: {:.0}/{:.0}\n", fuel.rest, fuel.maximum),
+                None => String::new(),
+            };
+        }
+
+        for mut span in &mut spans.p7() {
+            **span = match flight_assist {
+                Some(flight_assist) => format!("Flight assist: {flight_assist:?}\n"),
+                None => String::new(),
+            };
+        }
+
+        for gun in gun_query.iter().filter(|gun| gun.owner == entity) {
+            for mut span in &mut spans.p4() {
+                **span = if gun.is_reloading() {
+                    "Ammo: Reloading...\n".to_string()
+                } else if gun.heat.overheated {
+                    "Ammo: Overheated\n".to_string()
+                } else if let Some(magazine) = &gun.magazine {
+                    format!("Ammo: {}/{}\n", magazine.loaded, magazine.reserve)
+                } else {
+                    "Ammo: -\n".to_string()
+                };
+            }
+
+            // Lock-on is shown only for missiles
+            let lock_time: Option<f32> = match definitions.get(&gun.definition).map(|d| &d.kind) {
+                Some(WeaponKind::Missile(missile)) => Some(missile.lock_time),
+                _ => None,
+            };
+
+            for mut span in &mut spans.p5() {
+                **span = match (lock_time, gun.lock_on.target) {
+                    (None, _) => String::new(),
+                    (Some(_), None) => "Lock: -\n".to_string(),
+                    (Some(lock_time), Some(_)) if gun.lock_on.locked(lock_time).is_some() => {
+                        "Lock: LOCKED\n".to_string()
+                    }
+                    (Some(lock_time), Some(_)) => {
+                        format!("Lock: {:.0}%\n", gun.lock_on.progress / lock_time * 100.)
+                    }
+                };
+            }
+        }
     }
 
     for mut span in &mut spans.p3() {
@@ -3,8 +3,11 @@ use bevy::{
     color::palettes::basic::{BLUE, GREEN, RED, WHITE, YELLOW},
     prelude::*,
 };
-use spacerobo_commons::{Damage, DeathMessage, GameMode, Hp, KillCounter, Target};
-use spacerobo_player::PlayerCommonPlugin;
+use spacerobo_commons::{
+    Armor, ArmorReduction, CollisionDamage, Damage, DamageKind, DeathMessage, GameMode, Hp,
+    KillCounter, KillTallies, Pooled, Recycle, Resistances, Shield, Target,
+};
+use spacerobo_player::{Common as PlayerCommon, PlayerCommonPlugin};
 use spacerobo_target::Common as CommonTarget;
 
 pub struct ShootingRangePlugin;
@@ -15,6 +18,7 @@ impl Plugin for ShootingRangePlugin {
         app.add_message::<DeathMessage>();
         app.insert_resource(Gravity(Vec3::NEG_Y * 0.));
         app.insert_resource(KillCounter::default());
+        app.insert_resource(KillTallies::default());
         app.add_systems(
             OnEnter(GameMode::InGame),
             (setup_system, spawn_boundary_grid).run_if(in_state(GameMode::InGame)),
@@ -25,6 +29,8 @@ impl Plugin for ShootingRangePlugin {
                 // Systems
                 when_going_outside_system,
                 death_system,
+                shield_regeneration_system,
+                collision_damage_system,
             )
                 .run_if(in_state(GameMode::InGame)),
         );
@@ -36,8 +42,12 @@ fn setup_system(
     mut commands: Commands,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut kill_tallies: ResMut<KillTallies>,
     asset_server: Res<AssetServer>,
 ) {
+    // Reset KillTallies
+    kill_tallies.reset();
+
     // Light
     commands.spawn((
         PointLight {
@@ -49,6 +59,7 @@ fn setup_system(
     ));
 
     // Targets
+    let mut targets: Vec<Entity> = Vec::new();
     for i in 1..5 {
         for j in 1..5 {
             for k in 1..5 {
@@ -56,52 +67,52 @@ fn setup_system(
                 let j_float = j as f32;
                 let k_float = k as f32;
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     RED.into(),
                     Vec3::new(i_float * 10.0, j_float * 10.0, k_float * 10.0),
-                );
+                ));
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     WHITE.into(),
                     Vec3::new(i_float * 10.0, j_float * 10.0, k_float * -10.0),
-                );
+                ));
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     WHITE.into(),
                     Vec3::new(i_float * 10.0, j_float * -10.0, k_float * 10.0),
-                );
+                ));
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     GREEN.into(),
                     Vec3::new(i_float * 10.0, j_float * -10.0, k_float * -10.0),
-                );
+                ));
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     WHITE.into(),
                     Vec3::new(i_float * -10.0, j_float * 10.0, k_float * 10.0),
-                );
+                ));
 
-                CommonTarget::spawn(
+                let shielded: Entity = CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
@@ -109,8 +120,12 @@ fn setup_system(
                     YELLOW.into(),
                     Vec3::new(i_float * -10.0, j_float * 10.0, k_float * -10.0),
                 );
+                commands
+                    .entity(shielded)
+                    .insert(Shield::new(3000.0, 1000.0, 2.0));
+                targets.push(shielded);
 
-                CommonTarget::spawn(
+                let armored: Entity = CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
@@ -118,18 +133,46 @@ fn setup_system(
                     BLUE.into(),
                     Vec3::new(i_float * -10.0, j_float * -10.0, k_float * 10.0),
                 );
+                // Plated against impacts & blasts, but not beams
+                let plating: ArmorReduction = ArmorReduction {
+                    flat: 50.0,
+                    percent: 0.95,
+                };
+                commands.entity(armored).insert((
+                    Armor {
+                        kinetic: plating,
+                        explosive: plating,
+                        ..Default::default()
+                    },
+                    // Tough against impacts, weak against beams
+                    Resistances {
+                        kinetic: 0.5,
+                        energy: -0.5,
+                        ..Default::default()
+                    },
+                ));
+                targets.push(armored);
 
-                CommonTarget::spawn(
+                targets.push(CommonTarget::spawn(
                     &mut commands,
                     &mut meshes,
                     &asset_server,
                     &mut materials,
                     WHITE.into(),
                     Vec3::new(i_float * -10.0, j_float * -10.0, k_float * -10.0),
-                );
+                ));
             }
         }
     }
+
+    // Ramming hurts targets twice as much as the robo
+    let collision_damage: CollisionDamage = CollisionDamage {
+        factor: PlayerCommon::COLLISION_DAMAGE.factor * 2.0,
+        ..PlayerCommon::COLLISION_DAMAGE
+    };
+    for target in targets {
+        commands.entity(target).insert(collision_damage);
+    }
 }
 
 fn when_going_outside_system(
@@ -153,16 +196,52 @@ fn when_going_outside_system(
 pub fn death_system(
     mut commands: Commands,
     mut event_reader: MessageReader<DeathMessage>,
-    hp_query: Query<&Hp>,
+    mut kill_counter: ResMut<KillCounter>,
+    mut kill_tallies: ResMut<KillTallies>,
+    hp_query: Query<(&Hp, Has<Pooled>)>,
+    player_query: Query<(), With<PlayerCommon>>,
+    combatant_query: Query<(), Or<(With<CommonTarget>, With<PlayerCommon>)>>,
 ) {
+    let mut dead: Vec<Entity> = Vec::new();
+
     for death_event in event_reader.read() {
-        if let Ok(hp) = hp_query.get(death_event.entity) {
-            commands.entity(death_event.entity).despawn();
+        // An entity may receive some DeathMessages in a frame
+        if dead.contains(&death_event.entity) {
+            continue;
+        }
+
+        if let Ok((hp, pooled)) = hp_query.get(death_event.entity) {
+            if pooled {
+                commands.trigger(Recycle {
+                    entity: death_event.entity,
+                });
+            } else {
+                commands.entity(death_event.entity).despawn();
+            }
             if let Some(handle) = hp.death_sound.clone() {
-                commands.spawn(AudioPlayer::new(handle));
+                commands.spawn((AudioPlayer::new(handle), PlaybackSettings::DESPAWN));
+            }
+
+            dead.push(death_event.entity);
+            debug!(
+                "{:?} which has Hp component is dead by {:?}!!",
+                death_event.entity, death_event.instigator
+            );
+
+            // Only targets & robos count as kills, not bullets nor missiles shot down
+            if !combatant_query.contains(death_event.entity) {
+                continue;
             }
 
-            debug!("{:?} which has Hp component is dead!!", death_event.entity);
+            kill_tallies.record(death_event.entity, death_event.instigator);
+
+            // Credit the player's kills
+            if let Some(instigator) = death_event.instigator
+                && instigator != death_event.entity
+                && player_query.contains(instigator)
+            {
+                kill_counter.increment();
+            }
         }
     }
 }
@@ -236,20 +315,363 @@ pub fn spawn_boundary_grid(
     }
 }
 
+/// Applies damage to the shield first, then reduces the rest by the armor & the resistance to its kind, and decreases Hp by what is left
+#[allow(clippy::type_complexity)]
 fn apply_damage_system(
     damage: On<Damage>,
-    mut query: Query<&mut Hp>,
+    mut query: Query<(
+        &mut Hp,
+        Option<&mut Shield>,
+        Option<&Armor>,
+        Option<&Resistances>,
+    )>,
     mut event_writer: MessageWriter<DeathMessage>,
 ) {
-    if let Ok(mut hp) = query.get_mut(damage.target) {
+    if let Ok((mut hp, shield, armor, resistances)) = query.get_mut(damage.target) {
         if hp.rest <= 0. {
             return;
         }
 
-        hp.decrease(damage.amount);
+        let mut amount: f32 = damage.amount;
+        if let Some(mut shield) = shield {
+            amount = shield.absorb(amount);
+        }
+        if let Some(armor) = armor {
+            amount = armor.reduce(damage.kind, amount);
+        }
+        if let Some(resistances) = resistances {
+            amount = resistances.reduce(damage.kind, amount);
+        }
+
+        hp.decrease(amount);
 
         if hp.rest <= 0. {
-            event_writer.write(DeathMessage::new(damage.target));
+            event_writer.write(DeathMessage::killed_by(damage.target, damage.instigator));
+        }
+    }
+}
+
+/// Deals Collision damage to CollisionDamage bodies ramming each other, credited to the other body.
+/// Child colliders such as guns don't ram, as their hits are Melee damage.
+fn collision_damage_system(
+    mut commands: Commands,
+    mut collision_event_reader: MessageReader<CollisionStart>,
+    collisions: Collisions,
+    body_query: Query<&CollisionDamage>,
+) {
+    for event in collision_event_reader.read() {
+        if event.body1 != Some(event.collider1) || event.body2 != Some(event.collider2) {
+            continue;
+        }
+
+        let (body1, body2) = (event.collider1, event.collider2);
+        let (Ok(damage1), Ok(damage2)) = (body_query.get(body1), body_query.get(body2)) else {
+            continue;
+        };
+        let Some(contact) = collisions.get(body1, body2) else {
+            continue;
+        };
+        let impulse: f32 = contact.total_normal_impulse_magnitude();
+
+        for (target, collision_damage, instigator) in
+            [(body1, damage1, body2), (body2, damage2, body1)]
+        {
+            let amount: f32 = collision_damage.damage(impulse);
+
+            if amount > 0. {
+                commands.trigger(Damage {
+                    target,
+                    amount,
+                    kind: DamageKind::Collision,
+                    instigator: Some(instigator),
+                });
+            }
+        }
+    }
+}
+
+fn shield_regeneration_system(mut query: Query<&mut Shield>, time: Res<Time>) {
+    for mut shield in query.iter_mut() {
+        shield.regenerate(time.delta_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Kill attribution's unit tests
+    mod kill_attribution {
+        use crate::{apply_damage_system, death_system};
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            Damage, DamageKind, DeathMessage, Hp, KillCounter, KillTallies, Tally,
+        };
+        use spacerobo_player::Common as PlayerCommon;
+        use spacerobo_target::Common as CommonTarget;
+
+        fn app() -> App {
+            let mut app: App = App::new();
+            app.add_plugins(MinimalPlugins)
+                .add_message::<DeathMessage>()
+                .insert_resource(KillCounter::default())
+                .insert_resource(KillTallies::default())
+                .add_observer(apply_damage_system)
+                .add_systems(Update, death_system);
+
+            app
+        }
+
+        /// A kill by the player increments KillCounter
+        #[test]
+        fn player_kill() {
+            let mut app: App = app();
+            let player: Entity = app
+                .world_mut()
+                .spawn((PlayerCommon, Hp::new(10., None)))
+                .id();
+            let target: Entity = app
+                .world_mut()
+                .spawn((CommonTarget, Hp::new(10., None)))
+                .id();
+
+            app.world_mut().trigger(Damage {
+                target,
+                amount: 20.,
+                kind: DamageKind::Kinetic,
+                instigator: Some(player),
+            });
+            app.update();
+
+            assert!(app.world().get_entity(target).is_err());
+            assert_eq!(**app.world().resource::<KillCounter>(), 1);
+
+            let tallies = app.world().resource::<KillTallies>();
+            assert_eq!(
+                tallies.get(player),
+                Tally {
+                    kills: 1,
+                    deaths: 0
+                }
+            );
+            assert_eq!(
+                tallies.get(target),
+                Tally {
+                    kills: 0,
+                    deaths: 1
+                }
+            );
+        }
+
+        /// A kill by others does not increment KillCounter
+        #[test]
+        fn other_kill() {
+            let mut app: App = app();
+            let other: Entity = app.world_mut().spawn(Hp::new(10., None)).id();
+            let target: Entity = app
+                .world_mut()
+                .spawn((CommonTarget, Hp::new(10., None)))
+                .id();
+
+            app.world_mut().trigger(Damage {
+                target,
+                amount: 20.,
+                kind: DamageKind::Kinetic,
+                instigator: Some(other),
+            });
+            app.update();
+
+            assert_eq!(**app.world().resource::<KillCounter>(), 0);
+            assert_eq!(app.world().resource::<KillTallies>().get(other).kills, 1);
+        }
+
+        /// Shooting down your own bullet is neither tallied nor credited as a kill
+        #[test]
+        fn own_bullet() {
+            let mut app: App = app();
+            let player: Entity = app
+                .world_mut()
+                .spawn((PlayerCommon, Hp::new(10., None)))
+                .id();
+            let bullet: Entity = app.world_mut().spawn(Hp::ammo()).id();
+
+            app.world_mut().trigger(Damage {
+                target: bullet,
+                amount: 20.,
+                kind: DamageKind::Energy,
+                instigator: Some(player),
+            });
+            app.update();
+
+            assert!(app.world().get_entity(bullet).is_err());
+            assert_eq!(**app.world().resource::<KillCounter>(), 0);
+            assert_eq!(
+                app.world().resource::<KillTallies>().get(player),
+                Tally::default()
+            );
+        }
+    }
+
+    /// apply_damage_system's unit tests
+    mod apply_damage_system {
+        use crate::apply_damage_system;
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            Armor, ArmorReduction, Damage, DamageKind, DeathMessage, Hp, Resistances, Shield,
+        };
+
+        fn app() -> App {
+            let mut app: App = App::new();
+            app.add_plugins(MinimalPlugins)
+                .add_message::<DeathMessage>()
+                .add_observer(apply_damage_system);
+
+            app
+        }
+
+        fn damage(app: &mut App, target: Entity, amount: f32, kind: DamageKind) {
+            app.world_mut().trigger(Damage {
+                target,
+                amount,
+                kind,
+                instigator: None,
+            });
+        }
+
+        /// The shield absorbs damage first, and only the overflow reaches Hp
+        #[test]
+        fn shield() {
+            let mut app: App = app();
+            let target: Entity = app
+                .world_mut()
+                .spawn((Hp::new(100., None), Shield::new(50., 10., 2.)))
+                .id();
+
+            damage(&mut app, target, 30., DamageKind::Kinetic);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 100.);
+
+            damage(&mut app, target, 30., DamageKind::Kinetic);
+            assert_eq!(app.world().get::<Shield>(target).unwrap().rest, 0.);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 90.);
+        }
+
+        /// Armor reduces the damage which went through the shield, by the reduction of its kind
+        #[test]
+        fn armor() {
+            let mut app: App = app();
+            let target: Entity = app
+                .world_mut()
+                .spawn((
+                    Hp::new(100., None),
+                    Shield::new(20., 10., 2.),
+                    Armor {
+                        kinetic: ArmorReduction {
+                            flat: 10.,
+                            percent: 0.5,
+                        },
+                        ..Default::default()
+                    },
+                ))
+                .id();
+
+            // 20 absorbed, (50 - 10) * 0.5 = 20 to Hp
+            damage(&mut app, target, 70., DamageKind::Kinetic);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 80.);
+
+            // Energy isn't reduced
+            damage(&mut app, target, 30., DamageKind::Energy);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 50.);
+        }
+
+        /// Resistances reduce each damage kind separately
+        #[test]
+        fn resistances() {
+            let mut app: App = app();
+            let target: Entity = app
+                .world_mut()
+                .spawn((
+                    Hp::new(100., None),
+                    Resistances {
+                        kinetic: 0.5,
+                        melee: 1.0,
+                        ..Default::default()
+                    },
+                ))
+                .id();
+
+            damage(&mut app, target, 20., DamageKind::Kinetic);
+            damage(&mut app, target, 20000., DamageKind::Melee);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 90.);
+
+            damage(&mut app, target, 20., DamageKind::Energy);
+            assert_eq!(app.world().get::<Hp>(target).unwrap().rest, 70.);
+        }
+    }
+
+    /// collision_damage_system's unit tests
+    mod collision_damage_system {
+        use crate::{apply_damage_system, collision_damage_system};
+        use avian3d::prelude::*;
+        use bevy::prelude::*;
+        use spacerobo_commons::{
+            CollisionDamage, DeathMessage, Hp,
+            testing::{physics_app, update},
+        };
+        use std::time::Duration;
+
+        /// A body flying at the speed toward a static body 5 m ahead, returning both Hp after the impact
+        fn ram(speed: f32) -> (f32, f32) {
+            let mut app: App = physics_app(Duration::from_millis(16));
+            app.add_message::<DeathMessage>()
+                .add_observer(apply_damage_system)
+                .add_systems(Update, collision_damage_system);
+
+            let collision_damage: CollisionDamage = CollisionDamage {
+                factor: 1.0,
+                threshold: 25.0,
+            };
+            let rammer: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::default(),
+                    RigidBody::Dynamic,
+                    Collider::sphere(1.0),
+                    Mass(5.0),
+                    LinearVelocity(Vec3::NEG_Z * speed),
+                    Hp::new(1000., None),
+                    collision_damage,
+                ))
+                .id();
+            let target: Entity = app
+                .world_mut()
+                .spawn((
+                    Transform::from_xyz(0.0, 0.0, -5.0),
+                    RigidBody::Static,
+                    Collider::sphere(1.0),
+                    CollisionEventsEnabled,
+                    Hp::new(1000., None),
+                    collision_damage,
+                ))
+                .id();
+
+            update(&mut app, 60);
+
+            let hp = |entity: Entity| app.world().get::<Hp>(entity).unwrap().rest;
+            (hp(rammer), hp(target))
+        }
+
+        /// A hard impact damages both bodies by the impulse over the threshold
+        #[test]
+        fn impact() {
+            let (rammer, target) = ram(20.0);
+
+            // 5 kg * 20 m/s = 100 N·s, of which 75 is over the threshold
+            assert!((rammer - 925.).abs() < 5., "{rammer}");
+            assert_eq!(rammer, target);
+        }
+
+        /// A bump under the threshold is harmless
+        #[test]
+        fn bump() {
+            assert_eq!(ram(3.0), (1000., 1000.));
         }
     }
 }
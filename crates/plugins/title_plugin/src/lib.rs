@@ -1,5 +1,9 @@
 use bevy::prelude::*;
-use spacerobo_commons::GameMode;
+use spacerobo_commons::{
+    GameMode,
+    configs::GameConfigs,
+    input::{Action, ActionState},
+};
 
 pub struct TitlePlugin;
 
@@ -13,7 +17,16 @@ impl Plugin for TitlePlugin {
     }
 }
 
-fn setup_system(mut commands: Commands) {
+fn setup_system(mut commands: Commands, game_configs: Res<GameConfigs>) {
+    // The first binding of the action, such as "Space"
+    let binding = |action: Action| -> String {
+        game_configs
+            .player
+            .bindings(action)
+            .first()
+            .map_or("nothing".to_string(), |binding| binding.to_string())
+    };
+
     commands.spawn((DespawnOnExit(GameMode::Title), Camera2d));
 
     commands
@@ -29,22 +42,26 @@ fn setup_system(mut commands: Commands) {
             "Spacerobo v{}\n",
             env!("CARGO_PKG_VERSION")
         )))
-        .with_child(TextSpan::new(
-            "Press space key => Shooting Range\n".to_string(),
-        ))
-        .with_child(TextSpan::new("Press escape key => Exit...\n".to_string()));
+        .with_child(TextSpan::new(format!(
+            "Press {} => Shooting Range\n",
+            binding(Action::Start)
+        )))
+        .with_child(TextSpan::new(format!(
+            "Press {} => Exit...\n",
+            binding(Action::Quit)
+        )));
 }
 
 fn input_detection_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     mut gamemode: ResMut<NextState<GameMode>>,
     mut exit: MessageWriter<AppExit>,
 ) {
-    if keyboard.just_pressed(KeyCode::Space) {
+    if actions.just_pressed(Action::Start) {
         gamemode.set(GameMode::InGame);
     }
 
-    if keyboard.just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(Action::Quit) {
         exit.write(AppExit::Success);
     }
 }